@@ -0,0 +1,63 @@
+// Scans a generated multi-megabyte Lox script and reports throughput.
+// Run with `cargo bench --bench scanner`.
+use crafting_interpreters::scanner::Scanner;
+use std::hint::black_box;
+use std::time::Instant;
+
+const TARGET_BYTES: usize = 4 * 1024 * 1024;
+const ITERATIONS: u32 = 5;
+
+// A bit of everything the scanner handles, including non-ASCII text inside
+// strings and comments.
+const SNIPPET: &str = r#"
+// Line comment with ünïcode → text.
+/* Block comment /* nested */ spanning
+   two lines. */
+class Point < Base {
+    init(x, y) {
+        this.x = x;
+        this.y = y;
+    }
+
+    length() {
+        return (this.x * this.x + this.y * this.y) / 2.5;
+    }
+}
+
+fun fib(n) {
+    if (n <= 1) return n;
+    return fib(n - 2) + fib(n - 1);
+}
+
+var greeting = "héllo, wörld ✓ 日本語";
+for (var i = 0; i < 10; i = i + 1) {
+    if (i != 3 and !(i >= 7) or i == 9) print greeting;
+}
+"#;
+
+fn main() {
+    let mut source = String::with_capacity(TARGET_BYTES + SNIPPET.len());
+    while source.len() < TARGET_BYTES {
+        source.push_str(SNIPPET);
+    }
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+
+    let mut best = f64::MAX;
+    let mut token_count = 0;
+    for _ in 0..ITERATIONS {
+        let mut scanner = Scanner::new(source.clone());
+        let start = Instant::now();
+        let tokens = scanner.scan_tokens().expect("benchmark script should scan");
+        let elapsed = start.elapsed().as_secs_f64();
+        token_count = black_box(tokens).len();
+        best = best.min(elapsed);
+    }
+
+    println!(
+        "scanned {:.1} MB into {} tokens in {:.3}s ({:.1} MB/s)",
+        megabytes,
+        token_count,
+        best,
+        megabytes / best
+    );
+}
@@ -0,0 +1,22 @@
+// Generates the AST modules from src/ast.spec into OUT_DIR, where
+// src/expr.rs and src/stmt.rs `include!` them.
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[path = "src/ast_gen.rs"]
+mod ast_gen;
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/ast.spec");
+    println!("cargo:rerun-if-changed=src/ast_gen.rs");
+
+    let out_dir = env::var("OUT_DIR").expect("cargo sets OUT_DIR for build scripts");
+    let spec = fs::read_to_string("src/ast.spec").expect("failed to read src/ast.spec");
+    let asts = ast_gen::parse_spec(&spec).unwrap_or_else(|err| panic!("src/ast.spec: {err}"));
+    for ast in asts {
+        let path = Path::new(&out_dir).join(ast.file_name());
+        fs::write(&path, ast_gen::generate(&ast))
+            .unwrap_or_else(|err| panic!("failed to write {}: {err}", path.display()));
+    }
+}
@@ -0,0 +1,40 @@
+# Node definitions for the AST. build.rs generates a module from each
+# `[Base]` section at build time, which src/<base>.rs includes. After editing
+# this file, refresh the committed copies of the output with
+#
+#   cargo run --bin generate-ast src/ast.spec src/generated
+#
+# Each module holds a `Base` enum, one `<Name><Base>` struct per node and a
+# `<Base>Visitor<T>` trait. Nodes are written `Name : Type field, ...`, and
+# `use` lines are copied into the module as they are.
+
+[Expr]
+use crate::token::*;
+Assign        : Token name, Box<Expr> value, std::cell::Cell<Option<usize>> depth
+Binary        : Box<Expr> left, Token operator, Box<Expr> right
+Call          : Box<Expr> callee, Token paren, Vec<Expr> arguments
+Conditional   : Box<Expr> condition, Box<Expr> then_branch, Box<Expr> else_branch
+Get           : Box<Expr> object, Token name
+Grouping      : Box<Expr> expression, crate::span::Span span
+Interpolation : Vec<Expr> parts, crate::span::Span span
+Literal       : Option<Object> value, crate::span::Span span
+Logical       : Box<Expr> left, Token operator, Box<Expr> right
+Set           : Box<Expr> object, Token name, Box<Expr> value
+Super         : Token keyword, Token method, std::cell::Cell<Option<usize>> depth
+This          : Token keyword, std::cell::Cell<Option<usize>> depth
+Unary         : Token operator, Box<Expr> right
+Variable      : Token name, std::cell::Cell<Option<usize>> depth
+
+[Stmt]
+use crate::expr::*;
+use crate::token::*;
+use std::rc::Rc;
+Block      : Vec<Stmt> statements
+Class      : Token name, Option<VariableExpr> superclass, Vec<FunctionStmt> methods
+Expression : Expr expression
+Function   : Token name, Rc<Vec<Token>> params, Rc<Vec<Stmt>> body
+If         : Expr condition, Box<Stmt> then_branch, Option<Box<Stmt>> else_branch
+Print      : Expr expression
+Return     : Token keyword, Option<Expr> value
+Var        : Token name, Option<Expr> initializer
+While      : Expr condition, Box<Stmt> body
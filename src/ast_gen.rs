@@ -0,0 +1,235 @@
+// Turns the node definitions in src/ast.spec into Rust source. Shared by
+// build.rs, which generates the AST modules at build time, and the
+// `generate-ast` binary, which refreshes the committed copies in
+// src/generated.
+use std::fmt::{self, Write};
+
+#[derive(Debug)]
+struct TreeType {
+    base_class_name: String,
+    class_name: String,
+    // `(type, name)` pairs, in declaration order.
+    fields: Vec<(String, String)>,
+}
+
+// One `[Base]` section of the spec file.
+#[derive(Debug, Default)]
+pub struct AstSpec {
+    pub base_name: String,
+    imports: Vec<String>,
+    types: Vec<TreeType>,
+}
+
+impl AstSpec {
+    pub fn file_name(&self) -> String {
+        format!("{}.rs", self.base_name.to_lowercase())
+    }
+}
+
+pub fn parse_spec(spec: &str) -> Result<Vec<AstSpec>, String> {
+    let mut asts: Vec<AstSpec> = Vec::new();
+    for (number, line) in spec.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(base_name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            asts.push(AstSpec {
+                base_name: base_name.trim().to_string(),
+                ..AstSpec::default()
+            });
+            continue;
+        }
+        let Some(ast) = asts.last_mut() else {
+            return Err(spec_error(number, "expected a `[Base]` section first"));
+        };
+        if line.starts_with("use ") {
+            ast.imports.push(line.to_string());
+            continue;
+        }
+        let tree_type = line
+            .split_once(':')
+            .and_then(|(name, args)| tree_type(&ast.base_name, name, args))
+            .ok_or_else(|| spec_error(number, "expected `Name : Type field, ...`"))?;
+        ast.types.push(tree_type);
+    }
+    Ok(asts)
+}
+
+// Parses the `Name` and `Type field, ...` halves of a node definition, or
+// returns None if the name or any field's type or name is missing.
+fn tree_type(base_name: &str, name: &str, args: &str) -> Option<TreeType> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    let fields = split_fields(args)
+        .into_iter()
+        .map(|field| {
+            let (field_type, field_name) = field.trim().rsplit_once(' ')?;
+            let (field_type, field_name) = (field_type.trim(), field_name.trim());
+            if field_type.is_empty() || field_name.is_empty() {
+                return None;
+            }
+            Some((field_type.to_string(), field_name.to_string()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(TreeType {
+        base_class_name: name.to_string(),
+        class_name: format!("{name}{base_name}"),
+        fields,
+    })
+}
+
+fn spec_error(line: usize, message: &str) -> String {
+    format!("spec line {}: {}", line + 1, message)
+}
+
+// Splits a field list on the commas that aren't inside a generic type, so
+// fields like `HashMap<String, Token> names` stay in one piece.
+fn split_fields(args: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                fields.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&args[start..]);
+    fields
+}
+
+pub fn generate(ast: &AstSpec) -> String {
+    let mut out = String::new();
+    define_ast(&mut out, ast).expect("writing to a String can't fail");
+    out
+}
+
+fn define_ast(file: &mut String, ast: &AstSpec) -> fmt::Result {
+    let base_name = ast.base_name.as_str();
+    let lower_base = base_name.to_lowercase();
+    let tree_types = &ast.types;
+    writeln!(file, "use crate::error::*;")?;
+    for import in &ast.imports {
+        writeln!(file, "{import}")?;
+    }
+
+    writeln!(file, "\n#[derive(Debug, Clone, PartialEq)]")?;
+    writeln!(file, "pub enum {base_name} {{")?;
+
+    for tree in tree_types {
+        writeln!(file, "   {}({}),", tree.base_class_name, tree.class_name)?;
+    }
+    writeln!(file, "}}\n")?;
+
+    writeln!(file, "impl {base_name} {{\n   pub fn accept<T>(&self, {lower_base}_visitor: &dyn {base_name}Visitor<T>) -> Result<T, LoxError>{{")?;
+    writeln!(file, "       match self {{")?;
+
+    for tree in tree_types {
+        writeln!(
+            file,
+            "           {}::{}({}) => {}.accept({}_visitor),",
+            base_name, tree.base_class_name, lower_base, lower_base, lower_base
+        )?;
+    }
+    writeln!(file, "       }}")?;
+    writeln!(file, "   }}")?;
+    writeln!(file, "}}")?;
+
+    write!(file, "\n\n")?;
+
+    for t in tree_types {
+        writeln!(file, "\n#[derive(Debug, Clone, PartialEq)]")?;
+        writeln!(file, "pub struct {} {{", t.class_name)?;
+        for (field_type, name) in &t.fields {
+            writeln!(file, "    pub {name}: {field_type},")?;
+        }
+        writeln!(file, "}}\n")?;
+    }
+
+    writeln!(file, "pub trait {base_name}Visitor<T>{{")?;
+
+    for t in tree_types {
+        writeln!(
+            file,
+            "   fn visit_{}_{}(&self, {}: &{}) -> Result<T, LoxError>;",
+            t.base_class_name.to_lowercase(),
+            lower_base,
+            lower_base,
+            t.class_name
+        )?;
+    }
+    writeln!(file, "}}\n")?;
+
+    for t in tree_types {
+        write!(
+            file,
+            "impl {} {{\n   pub fn accept<T>(&self, visitor: &dyn {}Visitor<T>) -> Result<T, LoxError> {{\n       visitor.visit_{}_{}(self)\n   }}\n}}\n\n",
+            t.class_name,
+            base_name,
+            t.base_class_name.to_lowercase(),
+            lower_base,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    // The copies in src/generated are what readers and reviewers see, so
+    // they have to match what the build generates from the spec.
+    #[test]
+    fn committed_output_matches_spec() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let spec = fs::read_to_string(root.join("src/ast.spec")).unwrap();
+        for ast in parse_spec(&spec).unwrap() {
+            let path = root.join("src/generated").join(ast.file_name());
+            let committed = fs::read_to_string(&path).unwrap_or_default();
+            assert!(
+                committed == generate(&ast),
+                "{} is out of date with src/ast.spec; regenerate it with \
+                 `cargo run --bin generate-ast src/ast.spec src/generated`",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn generic_fields_are_not_split() {
+        let asts = parse_spec("[Node]\nPair : HashMap<String, Token> names, Token name").unwrap();
+        let source = generate(&asts[0]);
+        assert!(source.contains("pub names: HashMap<String, Token>,"));
+        assert!(source.contains("pub name: Token,"));
+    }
+
+    #[test]
+    fn malformed_node_definitions_are_reported_with_their_line() {
+        for line in [
+            "Foo : Token",
+            "Foo : Token name,",
+            "Foo : , Token name",
+            "Foo :",
+            " : Token name",
+            "Foo Bar : Token name",
+            "Foo",
+        ] {
+            let spec = format!("# comment\n[Node]\nGood : Token name\n{line}");
+            assert_eq!(
+                parse_spec(&spec).unwrap_err(),
+                "spec line 4: expected `Name : Type field, ...`",
+                "{line}"
+            );
+        }
+    }
+}
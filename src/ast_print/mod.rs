@@ -1,28 +1,51 @@
 use crate::error::*;
 use crate::expr::*;
-use crate::token::*;
-use crate::token_types::TokenType;
 // use token::*;
 
 
-struct AstPrinter;
+pub struct AstPrinter;
 
 impl AstPrinter {
-    fn print(&self, expr: &Expr) -> Result<String, LoxError> {
+    pub fn print(&self, expr: &Expr) -> Result<String, LoxError> {
         expr.accept(self)
     }
 
-    fn parenthesize(&self, name: &str, expr: &[&Box<Expr>])-> Result<String, LoxError> {
-        Ok(format!("({} {})", name, expr.iter().map(|e| e.accept(self)).collect::<Result<String, LoxError>>()?))
+    fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> Result<String, LoxError> {
+        let mut builder = format!("({name}");
+        for expr in exprs {
+            builder = format!("{builder} {}", expr.accept(self)?);
+        }
+        Ok(format!("{builder})"))
     }
 }
 
 impl ExprVisitor<String> for AstPrinter {
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<String, LoxError> {
+        self.parenthesize(&format!("= {}", expr.name.lexeme), &[&expr.value])
+    }
     fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<String, LoxError> {
-        self.parenthesize(expr.operator.lexeme.as_str(), &vec![&expr.left, &expr.right])
+        self.parenthesize(expr.operator.lexeme.as_str(), &[&expr.left, &expr.right])
+    }
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<String, LoxError> {
+        let mut exprs = vec![expr.callee.as_ref()];
+        exprs.extend(expr.arguments.iter());
+        self.parenthesize("call", &exprs)
+    }
+    fn visit_conditional_expr(&self, expr: &ConditionalExpr) -> Result<String, LoxError> {
+        self.parenthesize(
+            "?:",
+            &[&expr.condition, &expr.then_branch, &expr.else_branch],
+        )
+    }
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<String, LoxError> {
+        self.parenthesize(&format!(". {}", expr.name.lexeme), &[&expr.object])
     }
     fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<String, LoxError> {
-        self.parenthesize("group", &vec![&expr.expression])
+        self.parenthesize("group", &[&expr.expression])
+    }
+    fn visit_interpolation_expr(&self, expr: &InterpolationExpr) -> Result<String, LoxError> {
+        let exprs: Vec<&Expr> = expr.parts.iter().collect();
+        self.parenthesize("interpolate", &exprs)
     }
     fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<String, LoxError> {
         if let Some(value) = &expr.value {
@@ -31,40 +54,25 @@ impl ExprVisitor<String> for AstPrinter {
            Ok( "nil".to_string())
         }
     }
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<String, LoxError> {
+        self.parenthesize(expr.operator.lexeme.as_str(), &[&expr.left, &expr.right])
+    }
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<String, LoxError> {
+        self.parenthesize(
+            &format!("= . {}", expr.name.lexeme),
+            &[&expr.object, &expr.value],
+        )
+    }
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<String, LoxError> {
+        Ok(format!("(super {})", expr.method.lexeme))
+    }
+    fn visit_this_expr(&self, _expr: &ThisExpr) -> Result<String, LoxError> {
+        Ok("this".to_string())
+    }
     fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<String, LoxError> {
-        self.parenthesize(expr.operator.lexeme.as_str(), &vec![&expr.right])
+        self.parenthesize(expr.operator.lexeme.as_str(), &[&expr.right])
+    }
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<String, LoxError> {
+        Ok(expr.name.lexeme.clone())
     }
-}
-
-pub fn test_printer() {
-    println!("Hello, world!");
-
-    let expression = Expr::Binary(BinaryExpr {
-        left: Box::new(Expr::Unary(UnaryExpr {
-            operator: Token {
-                token_type: TokenType::Minus,
-                lexeme: "-".to_string(),
-                literal: None,
-                line: 1
-            },
-            right: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(123.0))
-            }))
-        })),
-        operator: Token {
-            token_type: TokenType::Star,
-            lexeme: "*".to_string(),
-            literal: None,
-            line: 1
-        },
-        right: Box::new(Expr::Grouping(GroupingExpr {
-            expression: Box::new(Expr::Literal(LiteralExpr {
-                value: Some(Object::Num(45.67))
-            }))
-        }))
-    });
-    let printer = AstPrinter{};
-
-    let result = printer.print(&expression);
-    println!("{}", &result.unwrap());
 }
@@ -0,0 +1,41 @@
+use crate::error::LoxError;
+use crate::interpreter::Interpreter;
+use crate::value::Value;
+use std::fmt;
+use std::rc::Rc;
+
+pub trait LoxCallable: fmt::Display {
+    fn arity(&self) -> usize;
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, LoxError>;
+}
+
+// Wraps a callable so it can live inside an `Value`; two values are only
+// equal when they refer to the very same function.
+#[derive(Clone)]
+pub struct Callable {
+    pub func: Rc<dyn LoxCallable>,
+}
+
+impl Callable {
+    pub fn new(func: Rc<dyn LoxCallable>) -> Self {
+        Callable { func }
+    }
+}
+
+impl PartialEq for Callable {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.func, &other.func)
+    }
+}
+
+impl fmt::Debug for Callable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.func)
+    }
+}
+
+impl fmt::Display for Callable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.func)
+    }
+}
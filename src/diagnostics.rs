@@ -0,0 +1,231 @@
+use crate::error::LoxError;
+use std::fmt::Write;
+use std::io::{self, IsTerminal};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Severity {
+    fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+        }
+    }
+
+    fn colour(&self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => GREEN,
+            Severity::Help => CYAN,
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+
+// Renders errors against the source they came from, rustc style:
+//
+//   error: Unexpected character '@'.
+//    --> script.lox:2:9
+//     |
+//   2 | var a = @;
+//     |         ^
+//     = help: ...
+pub struct Renderer<'a> {
+    file_name: &'a str,
+    source: &'a str,
+    colour: bool,
+}
+
+impl<'a> Renderer<'a> {
+    // Colour is only used when stderr is a terminal, so redirected output
+    // stays free of escape codes.
+    pub fn new(file_name: &'a str, source: &'a str) -> Self {
+        Self {
+            file_name,
+            source,
+            colour: io::stderr().is_terminal(),
+        }
+    }
+
+    pub fn with_colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    pub fn emit(&self, error: &LoxError) {
+        eprint!("{}", self.render(error));
+    }
+
+    pub fn render(&self, error: &LoxError) -> String {
+        let mut out = String::new();
+        let details = error.details();
+        let severity = details.severity;
+        let _ = writeln!(
+            out,
+            "{}{}",
+            self.paint(severity.colour(), severity.label()),
+            self.paint(BOLD, &format!(": {}", details.message))
+        );
+
+        let line = details.span.map_or(details.line, |span| span.line);
+        let column = details.span.map(|span| span.column.max(1));
+        if line == 0 {
+            return out;
+        }
+
+        let gutter = " ".repeat(line.to_string().len());
+        let location = match column {
+            Some(column) => format!("{}:{}:{}", self.file_name, line, column),
+            None => format!("{}:{}", self.file_name, line),
+        };
+        let _ = writeln!(out, "{}{} {}", gutter, self.paint(BLUE, "-->"), location);
+
+        if let Some(text) = self.source.lines().nth(line - 1) {
+            let bar = self.paint(BLUE, "|");
+            let _ = writeln!(out, "{} {}", gutter, bar);
+            let _ = writeln!(
+                out,
+                "{} {} {}",
+                self.paint(BLUE, &line.to_string()),
+                bar,
+                text
+            );
+            if let (Some(span), Some(column)) = (details.span, column) {
+                let carets = "^".repeat(self.underline_width(text, column, span.len()));
+                let _ = writeln!(
+                    out,
+                    "{} {} {}{}",
+                    gutter,
+                    bar,
+                    " ".repeat(column - 1),
+                    self.paint(severity.colour(), &carets)
+                );
+            }
+        }
+
+        for (severity, note) in &details.notes {
+            let _ = writeln!(
+                out,
+                "{} {} {}: {}",
+                gutter,
+                self.paint(BLUE, "="),
+                self.paint(severity.colour(), severity.label()),
+                note
+            );
+        }
+        out
+    }
+
+    // Number of carets needed to underline a span of `len` bytes starting at
+    // `column`. Spans running past the end of the line are cut off there,
+    // and empty spans (like end of file) still get a single caret.
+    fn underline_width(&self, text: &str, column: usize, len: usize) -> usize {
+        let mut width = 0;
+        let mut bytes = 0;
+        for c in text.chars().skip(column - 1) {
+            if bytes >= len {
+                break;
+            }
+            bytes += c.len_utf8();
+            width += 1;
+        }
+        width.max(1)
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.colour {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::Span;
+
+    fn render(source: &str, error: &LoxError) -> String {
+        Renderer::new("test.lox", source)
+            .with_colour(false)
+            .render(error)
+    }
+
+    #[test]
+    fn span_is_underlined_under_its_column() {
+        let source = "print 1;\nvar a = @;";
+        let error = LoxError::scan_error(Span::new(17, 18, 2, 9), "Unexpected character '@'.");
+        assert_eq!(
+            render(source, &error),
+            "error: Unexpected character '@'.\n \
+             --> test.lox:2:9\n  \
+             |\n\
+             2 | var a = @;\n  \
+             |         ^\n"
+        );
+    }
+
+    #[test]
+    fn multi_byte_chars_get_one_caret_each() {
+        // The span is six bytes but only two chars wide.
+        let source = "x = \"é😀\";";
+        let error = LoxError::scan_error(Span::new(5, 11, 1, 6), "Odd text.");
+        let rendered = render(source, &error);
+        assert!(
+            rendered.ends_with("1 | x = \"é😀\";\n  |      ^^\n"),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn underline_stops_at_the_end_of_the_line() {
+        let source = "print \"never\nclosed";
+        let error = LoxError::scan_error(Span::new(6, source.len(), 1, 7), "Unterminated string.");
+        let rendered = render(source, &error);
+        assert!(
+            rendered.ends_with("1 | print \"never\n  |       ^^^^^^\n"),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn empty_span_gets_a_single_caret() {
+        let source = "print 1";
+        let error = LoxError::scan_error(Span::new(7, 7, 1, 8), "Expect ';' after value.");
+        let rendered = render(source, &error);
+        assert!(
+            rendered.ends_with("1 | print 1\n  |        ^\n"),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn help_and_notes_follow_the_snippet() {
+        let source = "var a = @;";
+        let error = LoxError::scan_error(Span::new(8, 9, 1, 9), "Unexpected character '@'.")
+            .with_help("remove it")
+            .with_note("seen here");
+        let rendered = render(source, &error);
+        assert!(
+            rendered.ends_with("  |         ^\n  = help: remove it\n  = note: seen here\n"),
+            "{rendered}"
+        );
+    }
+}
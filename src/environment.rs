@@ -0,0 +1,105 @@
+use crate::error::LoxError;
+use crate::token::Token;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn new_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    // Redefining an existing name is allowed; it simply replaces the old value.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, LoxError> {
+        if let Some(value) = self.values.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(LoxError::runtime_error(
+                name,
+                &format!("Undefined variable '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    // Reads a binding from this environment only, without consulting any
+    // enclosing scope.
+    pub fn get_here(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+
+    // Looks `name` up exactly `distance` environments out, as computed by the
+    // resolver, instead of walking the chain until it is found.
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value, LoxError> {
+        self.get_name_at(distance, &name.lexeme).ok_or_else(|| {
+            LoxError::runtime_error(name, &format!("Undefined variable '{}'.", name.lexeme))
+        })
+    }
+
+    // Like `get_at`, for names the language binds implicitly (`this`,
+    // `super`) and that therefore have no token of their own.
+    pub fn get_name_at(&self, distance: usize, name: &str) -> Option<Value> {
+        if distance == 0 {
+            return self.get_here(name);
+        }
+
+        self.enclosing
+            .as_ref()
+            .and_then(|enclosing| enclosing.borrow().get_name_at(distance - 1, name))
+    }
+
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), LoxError> {
+        if distance == 0 {
+            self.values.insert(name.lexeme.clone(), value);
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+            None => Err(LoxError::runtime_error(
+                name,
+                &format!("Undefined variable '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), LoxError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(LoxError::runtime_error(
+                name,
+                &format!("Undefined variable '{}'.", name.lexeme),
+            )),
+        }
+    }
+}
@@ -1,24 +1,172 @@
+use crate::diagnostics::Severity;
+use crate::span::Span;
+use crate::token::Token;
+use crate::token_types::TokenType;
+use std::fmt;
 
-#[derive(Debug)]
-pub struct LoxError {
-    pub had_error: bool,
+// sysexits(3) codes the interpreter exits with when a script fails.
+pub const EX_DATAERR: i32 = 65;
+pub const EX_SOFTWARE: i32 = 70;
+
+// What went wrong and where. Shared by every kind of `LoxError`.
+#[derive(Debug, Clone)]
+pub struct Details {
+    pub severity: Severity,
+    // Boxed to keep `Result<_, LoxError>` small.
+    pub token: Option<Box<Token>>,
+    pub line: usize,
+    // The source region the error is about, when it is known.
+    pub span: Option<Span>,
+    pub message: String,
+    // `= help: ...` and `= note: ...` lines shown under the source snippet.
+    pub notes: Vec<(Severity, String)>,
+}
+
+impl Details {
+    fn new(line: usize, message: &str) -> Self {
+        Self {
+            severity: Severity::Error,
+            token: None,
+            line,
+            span: None,
+            message: message.to_string(),
+            notes: Vec::new(),
+        }
+    }
+
+    fn at(token: &Token, message: &str) -> Self {
+        Self {
+            token: Some(Box::new(token.clone())),
+            span: Some(token.span),
+            ..Self::new(token.span.line, message)
+        }
+    }
+}
+
+// One variant per phase, so callers can tell a bad program (scan, parse
+// and resolve errors) from one that failed while running.
+#[derive(Debug, Clone)]
+pub enum LoxError {
+    ScanError(Details),
+    ParseError(Details),
+    ResolveError(Details),
+    RuntimeError(Details),
 }
 
 impl LoxError {
-    pub fn new() -> Self {
-        Self { had_error: false }
+    pub fn scan_error(span: Span, message: &str) -> Self {
+        LoxError::ScanError(Details {
+            span: Some(span),
+            ..Details::new(span.line, message)
+        })
+    }
+
+    pub fn parse_error(token: &Token, message: &str) -> Self {
+        LoxError::ParseError(Details::at(token, message))
+    }
+
+    pub fn resolve_error(token: &Token, message: &str) -> Self {
+        LoxError::ResolveError(Details::at(token, message))
+    }
+
+    // Warnings are reported like errors but never stop the program from running.
+    pub fn warning(token: &Token, message: &str) -> Self {
+        LoxError::ResolveError(Details {
+            severity: Severity::Warning,
+            ..Details::at(token, message)
+        })
+    }
+
+    pub fn runtime_error(token: &Token, message: &str) -> Self {
+        LoxError::RuntimeError(Details::at(token, message))
+    }
+
+    // For runtime errors raised where no token is at hand, like inside
+    // native functions or the bytecode VM.
+    pub fn runtime_error_on_line(line: usize, message: &str) -> Self {
+        LoxError::RuntimeError(Details::new(line, message))
+    }
+
+    pub fn details(&self) -> &Details {
+        match self {
+            LoxError::ScanError(details)
+            | LoxError::ParseError(details)
+            | LoxError::ResolveError(details)
+            | LoxError::RuntimeError(details) => details,
+        }
+    }
+
+    pub fn details_mut(&mut self) -> &mut Details {
+        match self {
+            LoxError::ScanError(details)
+            | LoxError::ParseError(details)
+            | LoxError::ResolveError(details)
+            | LoxError::RuntimeError(details) => details,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.details().message
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.details().severity
     }
 
-    pub fn had_error(&self) -> bool {
-        self.had_error
+    // False for warnings, which shouldn't stop the program from running.
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
     }
 
-    pub fn set_error(&mut self, error: bool) {
-        self.had_error = error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoxError::RuntimeError(_) => EX_SOFTWARE,
+            _ => EX_DATAERR,
+        }
     }
 
-    pub fn report(&mut self, line: usize, message: String) {
-        eprintln!("[ Error on line {} ] :  {}", line, message);
-        self.set_error(true);
+    // Points the error at `span` instead of the token it was raised at.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.details_mut().span = Some(span);
+        self
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.details_mut()
+            .notes
+            .push((Severity::Help, help.to_string()));
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.details_mut()
+            .notes
+            .push((Severity::Note, note.to_string()));
+        self
     }
 }
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            LoxError::ScanError(_) => "Scan",
+            LoxError::ParseError(_) => "Parse",
+            LoxError::ResolveError(_) => "Resolve",
+            LoxError::RuntimeError(_) => "Runtime",
+        };
+        let details = self.details();
+        let label = match details.severity {
+            Severity::Warning => "warning",
+            _ => "error",
+        };
+        write!(f, "[line {}] {} {}", details.line, kind, label)?;
+        match &details.token {
+            Some(token) if token.token_type == TokenType::Eof => write!(f, " at end")?,
+            Some(token) => write!(f, " at '{}'", token.lexeme)?,
+            None => {}
+        }
+        write!(f, ": {}", details.message)
+    }
+}
+
+impl std::error::Error for LoxError {}
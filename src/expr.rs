@@ -1,76 +1,28 @@
-use crate::error::*;
-use crate::token::*;
+// Generated at build time from the `[Expr]` section of src/ast.spec. A copy
+// of the output is kept in src/generated/expr.rs.
+include!(concat!(env!("OUT_DIR"), "/expr.rs"));
 
-pub enum Expr {
-   Binary(BinaryExpr),
-   Grouping(GroupingExpr),
-   Literal(LiteralExpr),
-   Unary(UnaryExpr),
-}
+use crate::span::Span;
 
 impl Expr {
-   pub fn accept<T>(&self, expr_visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError>{
-       match self {
-           Expr::Binary(expr) => expr.accept(expr_visitor),
-           Expr::Grouping(expr) => expr.accept(expr_visitor),
-           Expr::Literal(expr) => expr.accept(expr_visitor),
-           Expr::Unary(expr) => expr.accept(expr_visitor),
-       }
-   }
+    // The full source extent of the expression, from its first token to its
+    // last, so errors can point at exactly the offending subexpression.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Assign(expr) => expr.name.span.to(expr.value.span()),
+            Expr::Binary(expr) => expr.left.span().to(expr.right.span()),
+            Expr::Call(expr) => expr.callee.span().to(expr.paren.span),
+            Expr::Conditional(expr) => expr.condition.span().to(expr.else_branch.span()),
+            Expr::Get(expr) => expr.object.span().to(expr.name.span),
+            Expr::Grouping(expr) => expr.span,
+            Expr::Interpolation(expr) => expr.span,
+            Expr::Literal(expr) => expr.span,
+            Expr::Logical(expr) => expr.left.span().to(expr.right.span()),
+            Expr::Set(expr) => expr.object.span().to(expr.value.span()),
+            Expr::Super(expr) => expr.keyword.span.to(expr.method.span),
+            Expr::This(expr) => expr.keyword.span,
+            Expr::Unary(expr) => expr.operator.span.to(expr.right.span()),
+            Expr::Variable(expr) => expr.name.span,
+        }
+    }
 }
-
-
-
-pub struct BinaryExpr {
-    pub left:  Box<Expr>,
-    pub operator:  Token,
-    pub right:  Box<Expr>,
-}
-
-
-pub struct GroupingExpr {
-    pub expression:  Box<Expr>,
-}
-
-
-pub struct LiteralExpr {
-    pub value:  Option<Object>,
-}
-
-
-pub struct UnaryExpr {
-    pub operator:  Token,
-    pub right:  Box<Expr>,
-}
-
-pub trait ExprVisitor<T>{
-   fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<T, LoxError>;
-   fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<T, LoxError>;
-   fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<T, LoxError>;
-   fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<T, LoxError>;
-}
-
-impl BinaryExpr {
-   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
-       visitor.visit_binary_expr(&self)
-   }
-}
-
-impl GroupingExpr {
-   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
-       visitor.visit_grouping_expr(&self)
-   }
-}
-
-impl LiteralExpr {
-   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
-       visitor.visit_literal_expr(&self)
-   }
-}
-
-impl UnaryExpr {
-   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
-       visitor.visit_unary_expr(&self)
-   }
-}
-
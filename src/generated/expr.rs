@@ -0,0 +1,249 @@
+use crate::error::*;
+use crate::token::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+   Assign(AssignExpr),
+   Binary(BinaryExpr),
+   Call(CallExpr),
+   Conditional(ConditionalExpr),
+   Get(GetExpr),
+   Grouping(GroupingExpr),
+   Interpolation(InterpolationExpr),
+   Literal(LiteralExpr),
+   Logical(LogicalExpr),
+   Set(SetExpr),
+   Super(SuperExpr),
+   This(ThisExpr),
+   Unary(UnaryExpr),
+   Variable(VariableExpr),
+}
+
+impl Expr {
+   pub fn accept<T>(&self, expr_visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError>{
+       match self {
+           Expr::Assign(expr) => expr.accept(expr_visitor),
+           Expr::Binary(expr) => expr.accept(expr_visitor),
+           Expr::Call(expr) => expr.accept(expr_visitor),
+           Expr::Conditional(expr) => expr.accept(expr_visitor),
+           Expr::Get(expr) => expr.accept(expr_visitor),
+           Expr::Grouping(expr) => expr.accept(expr_visitor),
+           Expr::Interpolation(expr) => expr.accept(expr_visitor),
+           Expr::Literal(expr) => expr.accept(expr_visitor),
+           Expr::Logical(expr) => expr.accept(expr_visitor),
+           Expr::Set(expr) => expr.accept(expr_visitor),
+           Expr::Super(expr) => expr.accept(expr_visitor),
+           Expr::This(expr) => expr.accept(expr_visitor),
+           Expr::Unary(expr) => expr.accept(expr_visitor),
+           Expr::Variable(expr) => expr.accept(expr_visitor),
+       }
+   }
+}
+
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignExpr {
+    pub name: Token,
+    pub value: Box<Expr>,
+    pub depth: std::cell::Cell<Option<usize>>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryExpr {
+    pub left: Box<Expr>,
+    pub operator: Token,
+    pub right: Box<Expr>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallExpr {
+    pub callee: Box<Expr>,
+    pub paren: Token,
+    pub arguments: Vec<Expr>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalExpr {
+    pub condition: Box<Expr>,
+    pub then_branch: Box<Expr>,
+    pub else_branch: Box<Expr>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupingExpr {
+    pub expression: Box<Expr>,
+    pub span: crate::span::Span,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterpolationExpr {
+    pub parts: Vec<Expr>,
+    pub span: crate::span::Span,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiteralExpr {
+    pub value: Option<Object>,
+    pub span: crate::span::Span,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogicalExpr {
+    pub left: Box<Expr>,
+    pub operator: Token,
+    pub right: Box<Expr>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub value: Box<Expr>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuperExpr {
+    pub keyword: Token,
+    pub method: Token,
+    pub depth: std::cell::Cell<Option<usize>>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThisExpr {
+    pub keyword: Token,
+    pub depth: std::cell::Cell<Option<usize>>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnaryExpr {
+    pub operator: Token,
+    pub right: Box<Expr>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableExpr {
+    pub name: Token,
+    pub depth: std::cell::Cell<Option<usize>>,
+}
+
+pub trait ExprVisitor<T>{
+   fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<T, LoxError>;
+   fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<T, LoxError>;
+   fn visit_call_expr(&self, expr: &CallExpr) -> Result<T, LoxError>;
+   fn visit_conditional_expr(&self, expr: &ConditionalExpr) -> Result<T, LoxError>;
+   fn visit_get_expr(&self, expr: &GetExpr) -> Result<T, LoxError>;
+   fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<T, LoxError>;
+   fn visit_interpolation_expr(&self, expr: &InterpolationExpr) -> Result<T, LoxError>;
+   fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<T, LoxError>;
+   fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<T, LoxError>;
+   fn visit_set_expr(&self, expr: &SetExpr) -> Result<T, LoxError>;
+   fn visit_super_expr(&self, expr: &SuperExpr) -> Result<T, LoxError>;
+   fn visit_this_expr(&self, expr: &ThisExpr) -> Result<T, LoxError>;
+   fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<T, LoxError>;
+   fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<T, LoxError>;
+}
+
+impl AssignExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_assign_expr(self)
+   }
+}
+
+impl BinaryExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_binary_expr(self)
+   }
+}
+
+impl CallExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_call_expr(self)
+   }
+}
+
+impl ConditionalExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_conditional_expr(self)
+   }
+}
+
+impl GetExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_get_expr(self)
+   }
+}
+
+impl GroupingExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_grouping_expr(self)
+   }
+}
+
+impl InterpolationExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_interpolation_expr(self)
+   }
+}
+
+impl LiteralExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_literal_expr(self)
+   }
+}
+
+impl LogicalExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_logical_expr(self)
+   }
+}
+
+impl SetExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_set_expr(self)
+   }
+}
+
+impl SuperExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_super_expr(self)
+   }
+}
+
+impl ThisExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_this_expr(self)
+   }
+}
+
+impl UnaryExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_unary_expr(self)
+   }
+}
+
+impl VariableExpr {
+   pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_variable_expr(self)
+   }
+}
+
@@ -0,0 +1,164 @@
+use crate::error::*;
+use crate::expr::*;
+use crate::token::*;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+   Block(BlockStmt),
+   Class(ClassStmt),
+   Expression(ExpressionStmt),
+   Function(FunctionStmt),
+   If(IfStmt),
+   Print(PrintStmt),
+   Return(ReturnStmt),
+   Var(VarStmt),
+   While(WhileStmt),
+}
+
+impl Stmt {
+   pub fn accept<T>(&self, stmt_visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError>{
+       match self {
+           Stmt::Block(stmt) => stmt.accept(stmt_visitor),
+           Stmt::Class(stmt) => stmt.accept(stmt_visitor),
+           Stmt::Expression(stmt) => stmt.accept(stmt_visitor),
+           Stmt::Function(stmt) => stmt.accept(stmt_visitor),
+           Stmt::If(stmt) => stmt.accept(stmt_visitor),
+           Stmt::Print(stmt) => stmt.accept(stmt_visitor),
+           Stmt::Return(stmt) => stmt.accept(stmt_visitor),
+           Stmt::Var(stmt) => stmt.accept(stmt_visitor),
+           Stmt::While(stmt) => stmt.accept(stmt_visitor),
+       }
+   }
+}
+
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockStmt {
+    pub statements: Vec<Stmt>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassStmt {
+    pub name: Token,
+    pub superclass: Option<VariableExpr>,
+    pub methods: Vec<FunctionStmt>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpressionStmt {
+    pub expression: Expr,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionStmt {
+    pub name: Token,
+    pub params: Rc<Vec<Token>>,
+    pub body: Rc<Vec<Stmt>>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IfStmt {
+    pub condition: Expr,
+    pub then_branch: Box<Stmt>,
+    pub else_branch: Option<Box<Stmt>>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintStmt {
+    pub expression: Expr,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnStmt {
+    pub keyword: Token,
+    pub value: Option<Expr>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarStmt {
+    pub name: Token,
+    pub initializer: Option<Expr>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhileStmt {
+    pub condition: Expr,
+    pub body: Box<Stmt>,
+}
+
+pub trait StmtVisitor<T>{
+   fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<T, LoxError>;
+   fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<T, LoxError>;
+   fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<T, LoxError>;
+   fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<T, LoxError>;
+   fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<T, LoxError>;
+   fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<T, LoxError>;
+   fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<T, LoxError>;
+   fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<T, LoxError>;
+   fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<T, LoxError>;
+}
+
+impl BlockStmt {
+   pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_block_stmt(self)
+   }
+}
+
+impl ClassStmt {
+   pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_class_stmt(self)
+   }
+}
+
+impl ExpressionStmt {
+   pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_expression_stmt(self)
+   }
+}
+
+impl FunctionStmt {
+   pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_function_stmt(self)
+   }
+}
+
+impl IfStmt {
+   pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_if_stmt(self)
+   }
+}
+
+impl PrintStmt {
+   pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_print_stmt(self)
+   }
+}
+
+impl ReturnStmt {
+   pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_return_stmt(self)
+   }
+}
+
+impl VarStmt {
+   pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_var_stmt(self)
+   }
+}
+
+impl WhileStmt {
+   pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+       visitor.visit_while_stmt(self)
+   }
+}
+
@@ -0,0 +1,517 @@
+use crate::callable::*;
+use crate::environment::Environment;
+use crate::error::*;
+use crate::expr::*;
+use crate::lox_class::LoxClass;
+use crate::lox_function::LoxFunction;
+use crate::lox_instance::LoxInstance;
+use crate::native_functions::*;
+use crate::stmt::*;
+use crate::token::*;
+use crate::token_types::TokenType;
+use crate::value::Value;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// The deepest chain of Lox calls allowed. Every call recurses on the Rust
+// stack, so runaway recursion has to be stopped before it overflows that.
+pub const MAX_CALL_DEPTH: usize = 1024;
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: RefCell<Rc<RefCell<Environment>>>,
+    // Set by a `return` statement while it unwinds to the enclosing call.
+    returning: RefCell<Option<Value>>,
+    call_depth: Cell<usize>,
+}
+
+impl StmtVisitor<()> for Interpreter {
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<(), LoxError> {
+        let environment = Environment::new_enclosing(self.environment.borrow().clone());
+        self.execute_block(&stmt.statements, environment)
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<(), LoxError> {
+        let superclass = match &stmt.superclass {
+            Some(superclass) => match self.visit_variable_expr(superclass)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    return Err(LoxError::runtime_error(
+                        &superclass.name,
+                        "Superclass must be a class.",
+                    ))
+                }
+            },
+            None => None,
+        };
+
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .define(&stmt.name.lexeme, Value::Nil);
+
+        // Methods of a subclass close over an extra scope binding `super`.
+        let enclosing = self.environment.borrow().clone();
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new_enclosing(Rc::clone(&enclosing));
+            environment.define("super", Value::Class(Rc::clone(superclass)));
+            self.environment.replace(Rc::new(RefCell::new(environment)));
+        }
+
+        let mut methods = HashMap::new();
+        for method in &stmt.methods {
+            let function = LoxFunction::new(
+                method,
+                self.environment.borrow().clone(),
+                method.name.lexeme == "init",
+            );
+            methods.insert(method.name.lexeme.clone(), Rc::new(function));
+        }
+
+        self.environment.replace(enclosing);
+
+        let class = LoxClass::new(&stmt.name.lexeme, superclass, methods);
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .assign(&stmt.name, Value::Class(Rc::new(class)))?;
+        Ok(())
+    }
+
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<(), LoxError> {
+        self.evaluate(&stmt.expression)?;
+        Ok(())
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<(), LoxError> {
+        let function = LoxFunction::new(stmt, self.environment.borrow().clone(), false);
+        self.environment.borrow().borrow_mut().define(
+            &stmt.name.lexeme,
+            Value::Func(Callable::new(Rc::new(function))),
+        );
+        Ok(())
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<(), LoxError> {
+        if self.is_truthy(&self.evaluate(&stmt.condition)?) {
+            self.execute(&stmt.then_branch)
+        } else if let Some(else_branch) = &stmt.else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<(), LoxError> {
+        let value = self.evaluate(&stmt.expression)?;
+        println!("{}", value);
+        Ok(())
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<(), LoxError> {
+        let value = match &stmt.value {
+            Some(value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+        self.returning.replace(Some(value));
+        Ok(())
+    }
+
+    fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<(), LoxError> {
+        let value = match &stmt.initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .define(&stmt.name.lexeme, value);
+        Ok(())
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<(), LoxError> {
+        while self.is_truthy(&self.evaluate(&stmt.condition)?) {
+            self.execute(&stmt.body)?;
+            if self.is_returning() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ExprVisitor<Value> for Interpreter {
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<Value, LoxError> {
+        let value = self.evaluate(&expr.value)?;
+        match expr.depth.get() {
+            Some(distance) => self.environment.borrow().borrow_mut().assign_at(
+                distance,
+                &expr.name,
+                value.clone(),
+            )?,
+            None => self
+                .globals
+                .borrow_mut()
+                .assign(&expr.name, value.clone())?,
+        }
+        Ok(value)
+    }
+
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<Value, LoxError> {
+        let left = self.evaluate(&expr.left)?;
+        let right = self.evaluate(&expr.right)?;
+
+        // Type errors point at the whole expression, not just the operator.
+        let span = expr.left.span().to(expr.right.span());
+        self.binary_operation(&expr.operator, left, right)
+            .map_err(|err| err.with_span(span))
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<Value, LoxError> {
+        let callee = self.evaluate(&expr.callee)?;
+
+        let mut arguments = Vec::new();
+        for argument in &expr.arguments {
+            arguments.push(self.evaluate(argument)?);
+        }
+
+        let callable: &dyn LoxCallable = match &callee {
+            Value::Func(function) => function.func.as_ref(),
+            Value::Class(class) => class,
+            _ => {
+                return Err(LoxError::runtime_error(
+                    &expr.paren,
+                    "Can only call functions and classes.",
+                ))
+            }
+        };
+
+        if arguments.len() != callable.arity() {
+            return Err(LoxError::runtime_error(
+                &expr.paren,
+                &format!(
+                    "Expected {} arguments but got {}.",
+                    callable.arity(),
+                    arguments.len()
+                ),
+            ));
+        }
+
+        if self.call_depth.get() == MAX_CALL_DEPTH {
+            return Err(LoxError::runtime_error(&expr.paren, "Stack overflow."));
+        }
+        self.call_depth.set(self.call_depth.get() + 1);
+        let result = callable.call(self, arguments);
+        self.call_depth.set(self.call_depth.get() - 1);
+
+        result.map_err(|mut err| {
+            let details = err.details_mut();
+            if details.token.is_none() {
+                details.line = expr.paren.span.line;
+                details.span = Some(expr.paren.span);
+                details.token = Some(Box::new(expr.paren.clone()));
+            }
+            err
+        })
+    }
+
+    fn visit_conditional_expr(&self, expr: &ConditionalExpr) -> Result<Value, LoxError> {
+        if self.is_truthy(&self.evaluate(&expr.condition)?) {
+            self.evaluate(&expr.then_branch)
+        } else {
+            self.evaluate(&expr.else_branch)
+        }
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<Value, LoxError> {
+        match self.evaluate(&expr.object)? {
+            Value::Instance(instance) => LoxInstance::get(&instance, &expr.name),
+            _ => Err(LoxError::runtime_error(
+                &expr.name,
+                "Only instances have properties.",
+            )),
+        }
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<Value, LoxError> {
+        self.evaluate(&expr.expression)
+    }
+
+    fn visit_interpolation_expr(&self, expr: &InterpolationExpr) -> Result<Value, LoxError> {
+        let mut text = String::new();
+        for part in &expr.parts {
+            text.push_str(&self.evaluate(part)?.to_string());
+        }
+        Ok(Value::from(text))
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<Value, LoxError> {
+        Ok(expr.value.clone().map_or(Value::Nil, Value::from))
+    }
+
+    // Logical operators short-circuit and yield the deciding operand itself,
+    // not a coerced boolean.
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<Value, LoxError> {
+        let left = self.evaluate(&expr.left)?;
+
+        if expr.operator.token_type == TokenType::Or {
+            if self.is_truthy(&left) {
+                return Ok(left);
+            }
+        } else if !self.is_truthy(&left) {
+            return Ok(left);
+        }
+
+        self.evaluate(&expr.right)
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<Value, LoxError> {
+        let Value::Instance(instance) = self.evaluate(&expr.object)? else {
+            return Err(LoxError::runtime_error(
+                &expr.name,
+                "Only instances have fields.",
+            ));
+        };
+
+        let value = self.evaluate(&expr.value)?;
+        instance.borrow_mut().set(&expr.name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<Value, LoxError> {
+        let distance = expr.depth.get().unwrap_or(0);
+        let environment = self.environment.borrow().clone();
+
+        let Some(Value::Class(superclass)) = environment.borrow().get_name_at(distance, "super")
+        else {
+            return Err(LoxError::runtime_error(
+                &expr.keyword,
+                "Can't use 'super' in a class with no superclass.",
+            ));
+        };
+        // `this` is always bound in the scope just inside the one holding `super`.
+        let Some(Value::Instance(object)) = environment
+            .borrow()
+            .get_name_at(distance.saturating_sub(1), "this")
+        else {
+            return Err(LoxError::runtime_error(
+                &expr.keyword,
+                "Can't use 'super' outside of a class.",
+            ));
+        };
+
+        match superclass.find_method(&expr.method.lexeme) {
+            Some(method) => Ok(Value::Func(Callable::new(Rc::new(method.bind(object))))),
+            None => Err(LoxError::runtime_error(
+                &expr.method,
+                &format!("Undefined property '{}'.", expr.method.lexeme),
+            )),
+        }
+    }
+
+    fn visit_this_expr(&self, expr: &ThisExpr) -> Result<Value, LoxError> {
+        self.look_up_variable(&expr.keyword, expr.depth.get())
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<Value, LoxError> {
+        let right = self.evaluate(&expr.right)?;
+
+        match expr.operator.token_type {
+            TokenType::Minus => match right {
+                Value::Number(value) => Ok(Value::Number(-value)),
+                _ => Err(
+                    LoxError::runtime_error(&expr.operator, "Operand must be a number.")
+                        .with_span(expr.operator.span.to(expr.right.span())),
+                ),
+            },
+            TokenType::Bang => Ok(Value::from(!self.is_truthy(&right))),
+            _ => Err(LoxError::runtime_error(
+                &expr.operator,
+                "Unknown unary operator.",
+            )),
+        }
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<Value, LoxError> {
+        self.look_up_variable(&expr.name, expr.depth.get())
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let interpreter = Interpreter {
+            globals: Rc::clone(&globals),
+            environment: RefCell::new(globals),
+            returning: RefCell::new(None),
+            call_depth: Cell::new(0),
+        };
+
+        for native in standard_library() {
+            interpreter.define_native(native);
+        }
+        interpreter
+    }
+
+    // Exposes a Rust function to Lox code as a global, e.g. for embedders
+    // that want to hand scripts access to host functionality.
+    pub fn register_native<F>(&self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, LoxError> + 'static,
+    {
+        self.define_native(NativeFunction::new(name, arity, Rc::new(function)));
+    }
+
+    fn define_native(&self, native: NativeFunction) {
+        let name = native.name().to_string();
+        self.globals
+            .borrow_mut()
+            .define(&name, Value::Func(Callable::new(Rc::new(native))));
+    }
+
+    pub fn globals(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.globals)
+    }
+
+    pub fn interpret(&self, statements: &[Stmt]) -> Result<(), LoxError> {
+        for statement in statements {
+            self.execute(statement)?;
+            if self.take_return_value().is_some() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&self, stmt: &Stmt) -> Result<(), LoxError> {
+        stmt.accept(self)
+    }
+
+    // Runs `statements` in `environment`, restoring the previous environment
+    // afterwards even if one of them fails.
+    pub(crate) fn execute_block(
+        &self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<(), LoxError> {
+        let previous = self.environment.replace(Rc::new(RefCell::new(environment)));
+
+        let mut result = Ok(());
+        for statement in statements {
+            result = self.execute(statement);
+            if result.is_err() || self.is_returning() {
+                break;
+            }
+        }
+
+        self.environment.replace(previous);
+        result
+    }
+
+    // Locals are found at the distance the resolver computed; anything the
+    // resolver left unresolved is a global.
+    fn look_up_variable(&self, name: &Token, depth: Option<usize>) -> Result<Value, LoxError> {
+        match depth {
+            Some(distance) => self.environment.borrow().borrow().get_at(distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn is_returning(&self) -> bool {
+        self.returning.borrow().is_some()
+    }
+
+    pub(crate) fn take_return_value(&self) -> Option<Value> {
+        self.returning.take()
+    }
+
+    fn evaluate(&self, expr: &Expr) -> Result<Value, LoxError> {
+        expr.accept(self)
+    }
+
+    // Lox follows Ruby's rule: `false` and `nil` are falsey, everything else is truthy.
+    fn is_truthy(&self, object: &Value) -> bool {
+        !matches!(object, Value::Nil | Value::Bool(false))
+    }
+
+    fn is_equal(&self, left: &Value, right: &Value) -> bool {
+        left == right
+    }
+
+    fn binary_operation(
+        &self,
+        operator: &Token,
+        left: Value,
+        right: Value,
+    ) -> Result<Value, LoxError> {
+        match operator.token_type {
+            TokenType::Minus => {
+                let (left, right) = self.check_number_operands(operator, &left, &right)?;
+                Ok(Value::Number(left - right))
+            }
+            TokenType::Slash => {
+                let (left, right) = self.check_number_operands(operator, &left, &right)?;
+                Ok(Value::Number(left / right))
+            }
+            TokenType::Star => {
+                let (left, right) = self.check_number_operands(operator, &left, &right)?;
+                Ok(Value::Number(left * right))
+            }
+            TokenType::Plus => match (left, right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left + right)),
+                (Value::Str(left), Value::Str(right)) => {
+                    Ok(Value::from(format!("{left}{right}")))
+                }
+                _ => Err(LoxError::runtime_error(
+                    operator,
+                    "Operands must be two numbers or two strings.",
+                )),
+            },
+            TokenType::Greater => {
+                let (left, right) = self.check_number_operands(operator, &left, &right)?;
+                Ok(Value::from(left > right))
+            }
+            TokenType::GreaterEqual => {
+                let (left, right) = self.check_number_operands(operator, &left, &right)?;
+                Ok(Value::from(left >= right))
+            }
+            TokenType::Less => {
+                let (left, right) = self.check_number_operands(operator, &left, &right)?;
+                Ok(Value::from(left < right))
+            }
+            TokenType::LessEqual => {
+                let (left, right) = self.check_number_operands(operator, &left, &right)?;
+                Ok(Value::from(left <= right))
+            }
+            TokenType::Comma => Ok(right),
+            TokenType::BangEqual => Ok(Value::from(!self.is_equal(&left, &right))),
+            TokenType::EqualEqual => Ok(Value::from(self.is_equal(&left, &right))),
+            _ => Err(LoxError::runtime_error(
+                operator,
+                "Unknown binary operator.",
+            )),
+        }
+    }
+
+    fn check_number_operands(
+        &self,
+        operator: &Token,
+        left: &Value,
+        right: &Value,
+    ) -> Result<(f64, f64), LoxError> {
+        match (left, right) {
+            (Value::Number(left), Value::Number(right)) => Ok((*left, *right)),
+            _ => Err(LoxError::runtime_error(
+                operator,
+                "Operands must be numbers.",
+            )),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+pub mod ast_gen;
+pub mod ast_print;
+pub mod callable;
+pub mod diagnostics;
+pub mod environment;
+pub mod error;
+pub mod expr;
+pub mod interpreter;
+pub mod lox_class;
+pub mod lox_function;
+pub mod lox_instance;
+pub mod native_functions;
+pub mod parser;
+pub mod resolver;
+pub mod scanner;
+pub mod span;
+pub mod stmt;
+pub mod token;
+pub mod token_types;
+pub mod value;
+pub mod vm;
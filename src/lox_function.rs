@@ -0,0 +1,82 @@
+use crate::callable::LoxCallable;
+use crate::environment::Environment;
+use crate::error::LoxError;
+use crate::interpreter::Interpreter;
+use crate::lox_instance::LoxInstance;
+use crate::stmt::{FunctionStmt, Stmt};
+use crate::token::Token;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+pub struct LoxFunction {
+    name: Token,
+    params: Rc<Vec<Token>>,
+    body: Rc<Vec<Stmt>>,
+    // The environment active where the function was declared, kept alive so
+    // the body can still see it after the declaring scope has exited.
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: &FunctionStmt,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        LoxFunction {
+            name: declaration.name.clone(),
+            params: Rc::clone(&declaration.params),
+            body: Rc::clone(&declaration.body),
+            closure,
+            is_initializer,
+        }
+    }
+
+    // Returns a copy of this method whose closure has `this` bound to `instance`.
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut environment = Environment::new_enclosing(Rc::clone(&self.closure));
+        environment.define("this", Value::Instance(instance));
+        LoxFunction {
+            name: self.name.clone(),
+            params: Rc::clone(&self.params),
+            body: Rc::clone(&self.body),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+        }
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, LoxError> {
+        let mut environment = Environment::new_enclosing(Rc::clone(&self.closure));
+        for (param, argument) in self.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, argument);
+        }
+
+        interpreter.execute_block(&self.body, environment)?;
+        let value = interpreter.take_return_value();
+
+        // An initializer always hands back the instance, even on a bare `return;`.
+        if self.is_initializer {
+            return Ok(self
+                .closure
+                .borrow()
+                .get_here("this")
+                .unwrap_or(Value::Nil));
+        }
+        Ok(value.unwrap_or(Value::Nil))
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name.lexeme)
+    }
+}
@@ -1,44 +1,82 @@
-use ast_print::*;
-
-use crate::scanner::Scanner;
+use crafting_interpreters::diagnostics::Renderer;
+use crafting_interpreters::error::LoxError;
+use crafting_interpreters::interpreter::Interpreter;
+use crafting_interpreters::parser::Parser;
+use crafting_interpreters::resolver::Resolver;
+use crafting_interpreters::scanner::Scanner;
+use crafting_interpreters::token::Token;
+use crafting_interpreters::vm::VM;
 use std::fs;
 use std::io::{self, stdout, BufRead, Write};
-use std::{env, process};
+use std::{env, process, thread};
+
+// The tree-walker recurses on the Rust stack for every Lox call, so it runs
+// on a thread with enough room to reach `MAX_CALL_DEPTH` in a debug build.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+// The two interchangeable execution engines, picked with `--backend=`.
+enum Backend {
+    TreeWalker(Interpreter),
+    Vm(VM),
+}
 
-pub mod error;
-pub mod scanner;
-pub mod token;
-pub mod token_types;
-pub mod ast_print;
-pub mod expr;
-pub mod parser;
+impl Backend {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tree" => Some(Backend::TreeWalker(Interpreter::new())),
+            "vm" => Some(Backend::Vm(VM::new())),
+            _ => None,
+        }
+    }
+}
 
 fn main() {
+    let lox = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run_cli)
+        .expect("failed to start the interpreter thread");
+    if lox.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn run_cli() {
+    let mut backend_name = "tree".to_string();
+    let mut paths = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.strip_prefix("--backend=") {
+            Some(name) => backend_name = name.to_string(),
+            None => paths.push(arg),
+        }
+    }
 
-    test_printer();
-    let args: Vec<String> = env::args().collect();
+    let Some(mut backend) = Backend::from_name(&backend_name) else {
+        eprintln!(
+            "Unknown backend '{}': expected 'tree' or 'vm'.",
+            backend_name
+        );
+        process::exit(64);
+    };
 
-    match args.len() {
-        1 => run_prompt().expect("Failed to run the prompt"),
-        2 => run_file(&args[1]).expect("failed to run the file"),
+    match paths.as_slice() {
+        [] => run_prompt(&mut backend).expect("Failed to run the prompt"),
+        [path] => run_file(&mut backend, path).expect("failed to run the file"),
         _ => {
-            println!("Usage: lox [script]");
+            println!("Usage: lox [--backend=tree|vm] [script]");
             process::exit(64);
         }
     }
 }
 
-fn run_file(path: &String) -> io::Result<()> {
-    let contents = fs::read_to_string(&path)?;
-    for line in contents.lines() {
-        println!("{}", line);
+fn run_file(backend: &mut Backend, path: &String) -> io::Result<()> {
+    let contents = fs::read_to_string(path)?;
+    if let Err(err) = run(backend, path, &contents) {
+        process::exit(err.exit_code());
     }
-    run(contents.to_string());
-    // write!()
     Ok(())
 }
 
-fn run_prompt() -> io::Result<()> {
+fn run_prompt(backend: &mut Backend) -> io::Result<()> {
     let stdin = io::stdin();
     print!("> ");
     stdout().flush()?;
@@ -49,16 +87,67 @@ fn run_prompt() -> io::Result<()> {
                 if val.is_empty() {
                     break;
                 } else {
-                    run(val)
+                    // Errors have already been shown; the prompt carries on.
+                    let _ = run(backend, "<repl>", &val);
                 }
             }
-            Err(e) => eprintln!("Error: {}", e.to_string()),
+            Err(e) => eprintln!("Error: {}", e),
         }
+        print!("> ");
+        stdout().flush()?;
     }
     Ok(())
 }
 
-fn run(source: String) {
-    let mut scanner = Scanner::new(source);
-    let _tokens = scanner.scan_tokens();
+// Every diagnostic is shown as soon as it is found. The returned error is
+// the first one that stopped the run, and decides the exit code.
+fn run(backend: &mut Backend, file_name: &str, source: &str) -> Result<(), LoxError> {
+    let renderer = Renderer::new(file_name, source);
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = match scanner.scan_tokens() {
+        Ok(tokens) => tokens.clone(),
+        Err(diagnostics) => return Err(emit_all(&renderer, diagnostics)),
+    };
+
+    match backend {
+        Backend::TreeWalker(interpreter) => run_tree_walker(interpreter, &renderer, tokens),
+        Backend::Vm(vm) => vm
+            .interpret(&tokens)
+            .map_err(|diagnostics| emit_all(&renderer, diagnostics)),
+    }
+}
+
+fn run_tree_walker(
+    interpreter: &Interpreter,
+    renderer: &Renderer,
+    tokens: Vec<Token>,
+) -> Result<(), LoxError> {
+    let mut parser = Parser::new(tokens);
+    let statements = parser
+        .parse()
+        .map_err(|diagnostics| emit_all(renderer, diagnostics))?;
+
+    let diagnostics = Resolver::new().resolve_program(&statements);
+    if diagnostics.iter().any(LoxError::is_error) {
+        return Err(emit_all(renderer, diagnostics));
+    }
+    for warning in &diagnostics {
+        renderer.emit(warning);
+    }
+
+    interpreter
+        .interpret(&statements)
+        .inspect_err(|err| renderer.emit(err))
+}
+
+// Shows all of `diagnostics` (which hold at least one error) and returns the
+// first error among them.
+fn emit_all(renderer: &Renderer, diagnostics: Vec<LoxError>) -> LoxError {
+    for diagnostic in &diagnostics {
+        renderer.emit(diagnostic);
+    }
+    diagnostics
+        .into_iter()
+        .find(LoxError::is_error)
+        .expect("diagnostics should contain an error")
 }
@@ -0,0 +1,59 @@
+use crate::callable::LoxCallable;
+use crate::error::LoxError;
+use crate::interpreter::Interpreter;
+use crate::value::Value;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub type NativeFn = dyn Fn(&[Value]) -> Result<Value, LoxError>;
+
+// A function implemented in Rust and exposed to Lox code. Errors returned
+// without a token are attributed to the call site by the interpreter.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    function: Rc<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(name: &str, arity: usize, function: Rc<NativeFn>) -> Self {
+        NativeFunction {
+            name: name.to_string(),
+            arity,
+            function,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, LoxError> {
+        (self.function)(&arguments)
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
+
+// The natives every interpreter starts with.
+pub fn standard_library() -> Vec<NativeFunction> {
+    vec![NativeFunction::new("clock", 0, Rc::new(clock))]
+}
+
+fn clock(_arguments: &[Value]) -> Result<Value, LoxError> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| LoxError::runtime_error_on_line(0, &err.to_string()))?;
+    Ok(Value::Number(elapsed.as_secs_f64()))
+}
@@ -1,26 +1,505 @@
 use crate::error::*;
 use crate::expr::*;
+use crate::stmt::*;
 use crate::token::*;
 use crate::token_types::*;
+use std::cell::Cell;
+use std::rc::Rc;
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    diagnostics: Vec<LoxError>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    // Parses the whole program. A syntax error doesn't stop the parse: it is
+    // recorded, the parser skips ahead to the next statement and carries on,
+    // so every error in the file is reported in one go.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            statements.extend(self.declaration());
+        }
+        if !self.diagnostics.is_empty() {
+            return Err(std::mem::take(&mut self.diagnostics));
+        }
+        Ok(statements)
+    }
+
+    // Parses the tokens as a single expression, which must use all of them.
+    pub fn parse_expression(&mut self) -> Result<Expr, Vec<LoxError>> {
+        let result = self.expression().and_then(|expr| {
+            if self.is_at_end() {
+                Ok(expr)
+            } else {
+                Err(LoxError::parse_error(
+                    self.peek().unwrap(),
+                    "Expect end of expression.",
+                ))
+            }
+        });
+        match result {
+            Ok(expr) if self.diagnostics.is_empty() => Ok(expr),
+            Ok(_) => Err(std::mem::take(&mut self.diagnostics)),
+            Err(err) => {
+                self.diagnostics.push(err);
+                Err(std::mem::take(&mut self.diagnostics))
+            }
+        }
+    }
+
+    // Discards tokens until we are probably at the start of the next
+    // statement: just past a ';' or at a keyword that begins one.
+    fn synchronize(&mut self) {
+        self.advance();
+        while !self.is_at_end() {
+            if self.previous().token_type == TokenType::Semicolon {
+                return;
+            }
+            match self.peek().unwrap().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    // Records an error that leaves the parser in a known state, so there is
+    // no need to unwind and synchronize.
+    fn error(&mut self, error: LoxError) {
+        self.diagnostics.push(error);
+    }
+
+    // Recovers from a syntax error here rather than in `parse`, so an error
+    // inside a block or body skips only the statement it is in and the rest
+    // of the enclosing block is still parsed. Returns None after an error.
+    fn declaration(&mut self) -> Option<Stmt> {
+        match self.declaration_or_error() {
+            Ok(statement) => Some(statement),
+            Err(err) => {
+                self.diagnostics.push(err);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    fn declaration_or_error(&mut self) -> Result<Stmt, LoxError> {
+        if self.is_match(vec![TokenType::Class]) {
+            return self.class_declaration();
+        }
+        if self.is_match(vec![TokenType::Fun]) {
+            return self.function("function");
+        }
+        if self.is_match(vec![TokenType::Var]) {
+            return self.var_declaration();
+        }
+        self.statement()
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect class name.".to_string())?
+            .clone();
+
+        let superclass = if self.is_match(vec![TokenType::Less]) {
+            let name = self
+                .consume(TokenType::Identifier, "Expect superclass name.".to_string())?
+                .clone();
+            Some(VariableExpr {
+                name,
+                depth: Cell::new(None),
+            })
+        } else {
+            None
+        };
+        self.consume(
+            TokenType::LeftBrace,
+            "Expect '{' before class body.".to_string(),
+        )?;
+
+        let mut methods = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function_declaration("method")?);
+        }
+
+        self.consume(
+            TokenType::RightBrace,
+            "Expect '}' after class body.".to_string(),
+        )?;
+        Ok(Stmt::Class(ClassStmt {
+            name,
+            superclass,
+            methods,
+        }))
+    }
+
+    fn function(&mut self, kind: &str) -> Result<Stmt, LoxError> {
+        Ok(Stmt::Function(self.function_declaration(kind)?))
+    }
+
+    fn function_declaration(&mut self, kind: &str) -> Result<FunctionStmt, LoxError> {
+        let name = self
+            .consume(TokenType::Identifier, format!("Expect {kind} name."))?
+            .clone();
+
+        self.consume(
+            TokenType::LeftParen,
+            format!("Expect '(' after {kind} name."),
+        )?;
+        let mut params = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= 255 {
+                    let error = LoxError::parse_error(
+                        self.peek().unwrap(),
+                        "Can't have more than 255 parameters.",
+                    );
+                    self.error(error);
+                }
+                params.push(
+                    self.consume(TokenType::Identifier, "Expect parameter name.".to_string())?
+                        .clone(),
+                );
+                if !self.is_match(vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after parameters.".to_string(),
+        )?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            format!("Expect '{{' before {kind} body."),
+        )?;
+        let body = self.block()?;
+
+        Ok(FunctionStmt {
+            name,
+            params: Rc::new(params),
+            body: Rc::new(body),
+        })
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect variable name.".to_string())?
+            .clone();
+
+        let initializer = if self.is_match(vec![TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.".to_string(),
+        )?;
+        Ok(Stmt::Var(VarStmt { name, initializer }))
+    }
+
+    fn statement(&mut self) -> Result<Stmt, LoxError> {
+        if self.is_match(vec![TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.is_match(vec![TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.is_match(vec![TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.is_match(vec![TokenType::Print]) {
+            return self.print_statement();
+        }
+        if self.is_match(vec![TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.is_match(vec![TokenType::LeftBrace]) {
+            return Ok(Stmt::Block(BlockStmt {
+                statements: self.block()?,
+            }));
+        }
+        self.expression_statement()
+    }
+
+    // `for` has no node of its own: it is desugared into a `while` loop,
+    // wrapped in a block when it declares a loop variable.
+    fn for_statement(&mut self) -> Result<Stmt, LoxError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.".to_string())?;
+
+        let initializer = if self.is_match(vec![TokenType::Semicolon]) {
+            None
+        } else if self.is_match(vec![TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        let semicolon = self
+            .consume(
+                TokenType::Semicolon,
+                "Expect ';' after loop condition.".to_string(),
+            )?
+            .span;
+
+        let increment = if self.check(TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after for clauses.".to_string(),
+        )?;
+
+        let mut body = self.statement()?;
+
+        if let Some(increment) = increment {
+            body = Stmt::Block(BlockStmt {
+                statements: vec![
+                    body,
+                    Stmt::Expression(ExpressionStmt {
+                        expression: increment,
+                    }),
+                ],
+            });
+        }
+
+        let condition = condition.unwrap_or(Expr::Literal(LiteralExpr {
+            value: Some(Object::True),
+            span: semicolon,
+        }));
+        body = Stmt::While(WhileStmt {
+            condition,
+            body: Box::new(body),
+        });
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(BlockStmt {
+                statements: vec![initializer, body],
+            });
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, LoxError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.".to_string())?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after if condition.".to_string(),
+        )?;
+
+        let then_branch = Box::new(self.statement()?);
+        // A dangling `else` binds to the nearest `if`.
+        let else_branch = if self.is_match(vec![TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If(IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+        }))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, LoxError> {
+        self.consume(
+            TokenType::LeftParen,
+            "Expect '(' after 'while'.".to_string(),
+        )?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after condition.".to_string(),
+        )?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While(WhileStmt { condition, body }))
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, LoxError> {
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.".to_string())?;
+        Ok(Stmt::Print(PrintStmt { expression: value }))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().clone();
+        let value = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after return value.".to_string(),
+        )?;
+        Ok(Stmt::Return(ReturnStmt { keyword, value }))
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, LoxError> {
+        let expr = self.expression()?;
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after expression.".to_string(),
+        )?;
+        Ok(Stmt::Expression(ExpressionStmt { expression: expr }))
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, LoxError> {
+        let mut statements = Vec::new();
+
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            statements.extend(self.declaration());
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.".to_string())?;
+        Ok(statements)
     }
 
     fn expression(&mut self) -> Result<Expr, LoxError> {
-        self.equality()
+        self.comma()
+    }
+
+    // `a, b` evaluates both operands and yields `b`. It binds loosest of all,
+    // so places that list expressions, like call arguments, parse
+    // `assignment` instead.
+    fn comma(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.assignment()?;
+
+        while self.is_match(vec![TokenType::Comma]) {
+            let operator = self.previous().clone();
+            let right = self.assignment()?;
+            expr = Expr::Binary(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+        Ok(expr)
+    }
+
+    fn assignment(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.conditional()?;
+
+        if self.is_match(vec![TokenType::Equal]) {
+            let equals = self.previous().clone();
+            let target = expr.span();
+            // Assignment is right-associative, so recurse instead of looping.
+            let value = self.assignment()?;
+
+            return match expr {
+                Expr::Variable(VariableExpr { name, .. }) => Ok(Expr::Assign(AssignExpr {
+                    name,
+                    value: Box::new(value),
+                    depth: Cell::new(None),
+                })),
+                Expr::Get(GetExpr { object, name }) => Ok(Expr::Set(SetExpr {
+                    object,
+                    name,
+                    value: Box::new(value),
+                })),
+                // The parser isn't confused by this, so report it and keep
+                // going with the left-hand side.
+                _ => {
+                    self.error(
+                        LoxError::parse_error(&equals, "Invalid assignment target.")
+                            .with_span(target)
+                            .with_help("only variables and fields can be assigned to"),
+                    );
+                    Ok(expr)
+                }
+            };
+        }
+
+        Ok(expr)
+    }
+
+    // `a ? b : c`. Any expression may sit between `?` and `:`; the else
+    // branch recurses so that chained conditionals nest to the right.
+    fn conditional(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.or()?;
+
+        if self.is_match(vec![TokenType::Question]) {
+            let then_branch = self.expression()?;
+            self.consume(
+                TokenType::Colon,
+                "Expect ':' after then branch of conditional expression.".to_string(),
+            )?;
+            let else_branch = self.conditional()?;
+            return Ok(Expr::Conditional(ConditionalExpr {
+                condition: Box::new(expr),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            }));
+        }
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.and()?;
+
+        while self.is_match(vec![TokenType::Or]) {
+            let operator = self.previous().clone();
+            let right = self.and()?;
+            expr = Expr::Logical(LogicalExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.equality()?;
+
+        while self.is_match(vec![TokenType::And]) {
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+            expr = Expr::Logical(LogicalExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+        Ok(expr)
     }
 
     fn equality(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.comparison()?;
         while self.is_match(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator = *self.previous();
+            let operator = self.previous().clone();
             let right = self.comparison()?;
             expr = Expr::Binary(BinaryExpr {
                 left: Box::new(expr),
@@ -40,7 +519,7 @@ impl Parser {
             TokenType::GreaterEqual,
             TokenType::LessEqual,
         ]) {
-            let operator = self.previous();
+            let operator = self.previous().clone();
             let right = self.term()?;
             expr = Expr::Binary(BinaryExpr {
                 left: Box::new(expr),
@@ -55,7 +534,7 @@ impl Parser {
         let mut expr = self.factor()?;
 
         while self.is_match(vec![TokenType::Minus, TokenType::Plus]) {
-            let operator = self.previous();
+            let operator = self.previous().clone();
             let right = self.factor()?;
             expr = Expr::Binary(BinaryExpr {
                 left: Box::new(expr),
@@ -70,7 +549,7 @@ impl Parser {
         let mut expr = self.unary()?;
 
         while self.is_match(vec![TokenType::Slash, TokenType::Star]) {
-            let operator = self.previous();
+            let operator = self.previous().clone();
             let right = self.unary()?;
             expr = Expr::Binary(BinaryExpr {
                 left: Box::new(expr),
@@ -83,7 +562,7 @@ impl Parser {
 
     fn unary(&mut self) -> Result<Expr, LoxError> {
         if self.is_match(vec![TokenType::Bang, TokenType::Minus]) {
-            let operator = self.previous();
+            let operator = self.previous().clone();
             let right = self.unary()?;
             let expr = Expr::Unary(UnaryExpr {
                 operator,
@@ -92,54 +571,230 @@ impl Parser {
             return Ok(expr);
         }
 
-        return self.primary();
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.is_match(vec![TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.is_match(vec![TokenType::Dot]) {
+                let name = self
+                    .consume(
+                        TokenType::Identifier,
+                        "Expect property name after '.'.".to_string(),
+                    )?
+                    .clone();
+                expr = Expr::Get(GetExpr {
+                    object: Box::new(expr),
+                    name,
+                });
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, LoxError> {
+        let mut arguments = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= 255 {
+                    let error = LoxError::parse_error(
+                        self.peek().unwrap(),
+                        "Can't have more than 255 arguments.",
+                    );
+                    self.error(error);
+                }
+                arguments.push(self.assignment()?);
+                if !self.is_match(vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self
+            .consume(
+                TokenType::RightParen,
+                "Expect ')' after arguments.".to_string(),
+            )?
+            .clone();
+
+        Ok(Expr::Call(CallExpr {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        }))
     }
 
     fn primary(&mut self) -> Result<Expr, LoxError> {
         if self.is_match(vec![TokenType::False]) {
             let expr = Expr::Literal(LiteralExpr {
-                value: Some(Object::False)
+                value: Some(Object::False),
+                span: self.previous().span,
             });
             return Ok(expr);
         }
         if self.is_match(vec![TokenType::True]) {
             let expr = Expr::Literal(LiteralExpr {
-                value: Some(Object::True)
+                value: Some(Object::True),
+                span: self.previous().span,
             });
             return Ok(expr);
         }
         if self.is_match(vec![TokenType::Nil]) {
             let expr = Expr::Literal(LiteralExpr {
-                value: Some(Object::Nil)
+                value: Some(Object::Nil),
+                span: self.previous().span,
             });
             return Ok(expr);
         }
+        if self.is_match(vec![TokenType::Interpolation]) {
+            return self.interpolation();
+        }
         if self.is_match(vec![TokenType::Number, TokenType::String]) {
             let expr = Expr::Literal(LiteralExpr {
-                value: self.previous().literal
+                value: self.previous().literal.clone(),
+                span: self.previous().span,
             });
             return Ok(expr);
         }
 
+        if self.is_match(vec![TokenType::Super]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.".to_string())?;
+            let method = self
+                .consume(
+                    TokenType::Identifier,
+                    "Expect superclass method name.".to_string(),
+                )?
+                .clone();
+            return Ok(Expr::Super(SuperExpr {
+                keyword,
+                method,
+                depth: Cell::new(None),
+            }));
+        }
+
+        if self.is_match(vec![TokenType::This]) {
+            return Ok(Expr::This(ThisExpr {
+                keyword: self.previous().clone(),
+                depth: Cell::new(None),
+            }));
+        }
+
+        if self.is_match(vec![TokenType::Identifier]) {
+            return Ok(Expr::Variable(VariableExpr {
+                name: self.previous().clone(),
+                depth: Cell::new(None),
+            }));
+        }
+
         if self.is_match(vec![TokenType::LeftParen]) {
+            let open = self.previous().span;
             let expr = self.expression()?;
-            self.consume(TokenType::RightParen, "Expect ')' after expression.".to_string());
+            let close = self
+                .consume(
+                    TokenType::RightParen,
+                    "Expect ')' after expression.".to_string(),
+                )?
+                .span;
             return Ok(Expr::Grouping(GroupingExpr {
-                expression:  Box::new(expr),
-            }))
+                expression: Box::new(expr),
+                span: open.to(close),
+            }));
+        }
+
+        if let Some(expr) = self.missing_left_operand()? {
+            return Ok(expr);
+        }
+
+        Err(LoxError::parse_error(
+            self.peek().unwrap(),
+            "Expect expression.",
+        ))
+    }
+
+    // Error production for a binary operator with nothing on its left, like
+    // `+ 3`. The right operand is parsed at the operator's precedence and
+    // thrown away, so the parser stays in step after reporting it.
+    fn missing_left_operand(&mut self) -> Result<Option<Expr>, LoxError> {
+        let operand: fn(&mut Self) -> Result<Expr, LoxError> = match self.peek().unwrap().token_type
+        {
+            TokenType::Or => Self::and,
+            TokenType::And => Self::equality,
+            TokenType::BangEqual | TokenType::EqualEqual => Self::comparison,
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => Self::term,
+            TokenType::Plus => Self::factor,
+            TokenType::Slash | TokenType::Star => Self::unary,
+            _ => return Ok(None),
+        };
+        let operator = self.advance().clone();
+        let right = operand(self)?;
+        self.error(LoxError::parse_error(
+            &operator,
+            &format!("Missing left-hand operand for '{}'.", operator.lexeme),
+        ));
+        Ok(Some(right))
+    }
+
+    // The scanner splits `"a ${b} c"` into an `Interpolation` token for each
+    // segment ending in `${`, the tokens of each expression, and an
+    // `InterpolationEnd` token for the rest. Empty segments are left out of the parts.
+    fn interpolation(&mut self) -> Result<Expr, LoxError> {
+        let start = self.previous().span;
+        let mut parts = Vec::new();
+        loop {
+            self.push_segment(&mut parts);
+            if self.check(TokenType::InterpolationEnd) {
+                return Err(LoxError::parse_error(
+                    self.peek().unwrap(),
+                    "Expect expression in string interpolation.",
+                ));
+            }
+            parts.push(self.expression()?);
+            if !self.is_match(vec![TokenType::Interpolation]) {
+                break;
+            }
         }
+        let end = self
+            .consume(
+                TokenType::InterpolationEnd,
+                "Expect '}' after interpolated expression.".to_string(),
+            )?
+            .span;
+        self.push_segment(&mut parts);
+        Ok(Expr::Interpolation(InterpolationExpr {
+            parts,
+            span: start.to(end),
+        }))
+    }
 
-        return Err(LoxError::new())
+    fn push_segment(&self, parts: &mut Vec<Expr>) {
+        let token = self.previous();
+        if token.literal != Some(Object::Str(String::new())) {
+            parts.push(Expr::Literal(LiteralExpr {
+                value: token.literal.clone(),
+                span: token.span,
+            }));
+        }
     }
 
     fn consume(&mut self, ttype: TokenType, message: String) -> Result<&Token, LoxError> {
-        if self.check(ttype){
+        if self.check(ttype) {
             return Ok(self.advance());
         }
-        return Err(LoxError::new())
+        Err(LoxError::parse_error(self.peek().unwrap(), &message))
     }
 
-    fn is_match(&self, token_types: Vec<TokenType>) -> bool {
+    fn is_match(&mut self, token_types: Vec<TokenType>) -> bool {
         for ttype in token_types {
             if self.check(ttype.clone()) {
                 self.advance();
@@ -175,3 +830,265 @@ impl Parser {
         self.tokens.get(self.current - 1).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_print::AstPrinter;
+    use crate::scanner::Scanner;
+
+    // Every binary operator, from lowest to highest precedence. Operators on
+    // the same row share a precedence level.
+    const LEVELS: [&[&str]; 6] = [
+        &["or"],
+        &["and"],
+        &["==", "!="],
+        &["<", "<=", ">", ">="],
+        &["+", "-"],
+        &["*", "/"],
+    ];
+
+    fn parse(source: &str) -> String {
+        let tokens = Scanner::new(source.to_string())
+            .scan_tokens()
+            .expect("source should scan")
+            .clone();
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("source should parse");
+        AstPrinter.print(&expr).unwrap()
+    }
+
+    #[test]
+    fn operators_on_the_same_level_associate_left() {
+        for level in LEVELS {
+            for first in level {
+                for second in level {
+                    assert_eq!(
+                        parse(&format!("a {first} b {second} c")),
+                        format!("({second} ({first} a b) c)"),
+                        "operators {first} and {second}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn higher_precedence_binds_tighter() {
+        for (i, lower_level) in LEVELS.iter().enumerate() {
+            for higher_level in &LEVELS[i + 1..] {
+                for low in lower_level.iter() {
+                    for high in higher_level.iter() {
+                        assert_eq!(
+                            parse(&format!("a {low} b {high} c")),
+                            format!("({low} a ({high} b c))"),
+                            "operators {low} and {high}"
+                        );
+                        assert_eq!(
+                            parse(&format!("a {high} b {low} c")),
+                            format!("({low} ({high} a b) c)"),
+                            "operators {high} and {low}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grouping_overrides_precedence() {
+        assert_eq!(parse("(a + b) * c"), "(* (group (+ a b)) c)");
+        assert_eq!(parse("a - (b - c)"), "(- a (group (- b c)))");
+    }
+
+    #[test]
+    fn unary_binds_tighter_than_binary() {
+        assert_eq!(parse("-a * b"), "(* (- a) b)");
+        assert_eq!(parse("!a == b"), "(== (! a) b)");
+    }
+
+    #[test]
+    fn assignment_is_right_associative() {
+        assert_eq!(parse("a = b = c"), "(= a (= b c))");
+        assert_eq!(parse("a = b or c"), "(= a (or b c))");
+    }
+
+    #[test]
+    fn comma_binds_loosest() {
+        assert_eq!(parse("a = 1, b = 2, c"), "(, (, (= a 1) (= b 2)) c)");
+        assert_eq!(parse("f(a, b)"), "(call f a b)");
+        assert_eq!(parse("f((a, b))"), "(call f (group (, a b)))");
+    }
+
+    #[test]
+    fn conditional_is_right_associative() {
+        assert_eq!(parse("a ? b : c ? d : e"), "(?: a b (?: c d e))");
+        assert_eq!(parse("a or b ? c, d : e"), "(?: (or a b) (, c d) e)");
+        assert_eq!(parse("x = a ? b : c"), "(= x (?: a b c))");
+    }
+
+    #[test]
+    fn binary_operator_without_left_operand_is_reported() {
+        // `-` is skipped: with nothing on its left it is unary negation.
+        let binary_only = LEVELS.iter().flat_map(|level| level.iter());
+        for op in binary_only.filter(|op| **op != "-") {
+            let tokens = Scanner::new(format!("{op} a"))
+                .scan_tokens()
+                .unwrap()
+                .clone();
+            let Err(errors) = Parser::new(tokens).parse_expression() else {
+                panic!("missing operand for {op} should be rejected");
+            };
+            assert_eq!(errors.len(), 1, "operator {op}");
+            assert_eq!(
+                errors[0].message(),
+                format!("Missing left-hand operand for '{op}'.")
+            );
+        }
+    }
+
+    #[test]
+    fn missing_left_operand_does_not_stop_the_parse() {
+        let tokens = Scanner::new("print + 3; print == 4; print 5 *;".to_string())
+            .scan_tokens()
+            .unwrap()
+            .clone();
+        let Err(errors) = Parser::new(tokens).parse() else {
+            panic!("statements should be rejected");
+        };
+        let messages: Vec<&str> = errors.iter().map(LoxError::message).collect();
+        assert_eq!(
+            messages,
+            [
+                "Missing left-hand operand for '+'.",
+                "Missing left-hand operand for '=='.",
+                "Expect expression.",
+            ]
+        );
+    }
+
+    #[test]
+    fn one_parse_reports_every_syntax_error() {
+        let source = "var = 1;\nprint (2;\nfun f( {}\nclass { }\nprint 3;\nreturn 4 5;";
+        let tokens = Scanner::new(source.to_string())
+            .scan_tokens()
+            .unwrap()
+            .clone();
+        let Err(errors) = Parser::new(tokens).parse() else {
+            panic!("statements should be rejected");
+        };
+        let located: Vec<(&str, usize)> = errors
+            .iter()
+            .map(|err| (err.message(), err.details().line))
+            .collect();
+        assert_eq!(
+            located,
+            [
+                ("Expect variable name.", 1),
+                ("Expect ')' after expression.", 2),
+                ("Expect parameter name.", 3),
+                ("Expect class name.", 4),
+                ("Expect ';' after return value.", 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_inside_blocks_do_not_end_the_block() {
+        let source = "fun f() {\n  var = 1;\n  print 2;\n}\n\
+                      class A {\n  m() { print ; return; }\n}\n\
+                      { var x = ; print x; }\n\
+                      while (true) { print -; }\n\
+                      print 3;";
+        let tokens = Scanner::new(source.to_string())
+            .scan_tokens()
+            .unwrap()
+            .clone();
+        let Err(errors) = Parser::new(tokens).parse() else {
+            panic!("statements should be rejected");
+        };
+        let located: Vec<(&str, usize)> = errors
+            .iter()
+            .map(|err| (err.message(), err.details().line))
+            .collect();
+        assert_eq!(
+            located,
+            [
+                ("Expect variable name.", 2),
+                ("Expect expression.", 6),
+                ("Expect expression.", 8),
+                ("Expect expression.", 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn trailing_tokens_are_an_error() {
+        let tokens = Scanner::new("a + b c".to_string())
+            .scan_tokens()
+            .unwrap()
+            .clone();
+        let Err(errors) = Parser::new(tokens).parse_expression() else {
+            panic!("trailing tokens should be rejected");
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message(), "Expect end of expression.");
+    }
+
+    // The source text an expression's span covers, with its start column.
+    fn spanned(source: &str) -> (&str, usize) {
+        let tokens = Scanner::new(source.to_string())
+            .scan_tokens()
+            .expect("source should scan")
+            .clone();
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("source should parse");
+        let span = expr.span();
+        (&source[span.start..span.end], span.column)
+    }
+
+    #[test]
+    fn expressions_span_from_first_to_last_token() {
+        assert_eq!(spanned("  a + b * c"), ("a + b * c", 3));
+        assert_eq!(spanned("f(1, g(2))"), ("f(1, g(2))", 1));
+        assert_eq!(spanned("a ? b : c ? d : e"), ("a ? b : c ? d : e", 1));
+        assert_eq!(spanned("-(x)"), ("-(x)", 1));
+        assert_eq!(spanned("obj.field = 1"), ("obj.field = 1", 1));
+    }
+
+    #[test]
+    fn spans_after_non_ascii_text_stay_on_char_boundaries() {
+        assert_eq!(spanned("\"é\" + \"😀\""), ("\"é\" + \"😀\"", 1));
+        assert_eq!(spanned("\"é😀\" == x"), ("\"é😀\" == x", 1));
+        assert_eq!(spanned("\"é\", f(\"😀\")"), ("\"é\", f(\"😀\")", 1));
+    }
+
+    #[test]
+    fn interpolation_parts() {
+        assert_eq!(parse(r#""Hello ${name}!""#), "(interpolate Hello  name !)");
+        assert_eq!(parse(r#""${a}${b}""#), "(interpolate a b)");
+        assert_eq!(
+            parse(r#""${a ? "${b}" : c}""#),
+            "(interpolate (?: a (interpolate b) c))"
+        );
+        assert_eq!(parse(r#""${"x"}""#), "(interpolate x)");
+        assert_eq!(parse(r#""<${"a" + b}>""#), "(interpolate < (+ a b) >)");
+    }
+
+    #[test]
+    fn empty_interpolation_is_an_error() {
+        let tokens = Scanner::new(r#""a ${}""#.to_string())
+            .scan_tokens()
+            .unwrap()
+            .clone();
+        let Err(errors) = Parser::new(tokens).parse_expression() else {
+            panic!("an empty interpolation should be rejected");
+        };
+        assert_eq!(
+            errors[0].message(),
+            "Expect expression in string interpolation."
+        );
+    }
+}
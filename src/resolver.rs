@@ -0,0 +1,572 @@
+use crate::error::*;
+use crate::expr::*;
+use crate::span::Span;
+use crate::stmt::*;
+use crate::token::*;
+use crate::token_types::TokenType;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+struct Binding {
+    name: Token,
+    // False between declaration and definition, i.e. while the initializer runs.
+    defined: bool,
+    used: bool,
+    // Only `var` declarations are worth warning about; parameters and local
+    // functions are routinely left unused.
+    warn_if_unused: bool,
+}
+
+// Walks the AST once before execution and records, on every variable
+// reference, how many scopes out its binding lives. Globals are left
+// unresolved and looked up dynamically by the interpreter.
+pub struct Resolver {
+    scopes: RefCell<Vec<HashMap<String, Binding>>>,
+    current_function: Cell<FunctionType>,
+    current_class: Cell<ClassType>,
+    diagnostics: RefCell<Vec<LoxError>>,
+}
+
+impl StmtVisitor<()> for Resolver {
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<(), LoxError> {
+        self.begin_scope();
+        self.resolve(&stmt.statements);
+        self.end_scope();
+        Ok(())
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<(), LoxError> {
+        let enclosing_class = self.current_class.replace(ClassType::Class);
+
+        self.declare(&stmt.name, false);
+        self.define(&stmt.name);
+
+        if let Some(superclass) = &stmt.superclass {
+            if superclass.name.lexeme == stmt.name.lexeme {
+                self.error(&superclass.name, "A class can't inherit from itself.");
+            }
+
+            self.current_class.set(ClassType::Subclass);
+            let _ = self.visit_variable_expr(superclass);
+
+            // Subclass methods close over an extra scope holding `super`.
+            self.begin_scope();
+            self.define_implicit("super");
+        }
+
+        // Methods close over a scope holding `this`.
+        self.begin_scope();
+        self.define_implicit("this");
+
+        for method in &stmt.methods {
+            let declaration = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, declaration);
+        }
+
+        self.end_scope();
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class.set(enclosing_class);
+        Ok(())
+    }
+
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.expression);
+        Ok(())
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<(), LoxError> {
+        // Defined eagerly so the function can refer to itself recursively.
+        self.declare(&stmt.name, false);
+        self.define(&stmt.name);
+
+        self.resolve_function(stmt, FunctionType::Function);
+        Ok(())
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.condition);
+        self.resolve_stmt(&stmt.then_branch);
+        if let Some(else_branch) = &stmt.else_branch {
+            self.resolve_stmt(else_branch);
+        }
+        Ok(())
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.expression);
+        Ok(())
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<(), LoxError> {
+        if self.current_function.get() == FunctionType::None {
+            self.error(&stmt.keyword, "Can't return from top-level code.");
+        }
+
+        if let Some(value) = &stmt.value {
+            if self.current_function.get() == FunctionType::Initializer {
+                self.error(&stmt.keyword, "Can't return a value from an initializer.");
+            }
+            self.resolve_expr(value);
+        }
+        Ok(())
+    }
+
+    fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<(), LoxError> {
+        self.declare(&stmt.name, true);
+        if let Some(initializer) = &stmt.initializer {
+            self.resolve_expr(initializer);
+        }
+        self.define(&stmt.name);
+        Ok(())
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.condition);
+        self.resolve_stmt(&stmt.body);
+        Ok(())
+    }
+}
+
+impl ExprVisitor<()> for Resolver {
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.value);
+        self.resolve_local(&expr.depth, &expr.name, false);
+        Ok(())
+    }
+
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.callee);
+        for argument in &expr.arguments {
+            self.resolve_expr(argument);
+        }
+        Ok(())
+    }
+
+    fn visit_conditional_expr(&self, expr: &ConditionalExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.condition);
+        self.resolve_expr(&expr.then_branch);
+        self.resolve_expr(&expr.else_branch);
+        Ok(())
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<(), LoxError> {
+        // Properties are looked up dynamically, so only the object is resolved.
+        self.resolve_expr(&expr.object);
+        Ok(())
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.expression);
+        Ok(())
+    }
+
+    fn visit_interpolation_expr(&self, expr: &InterpolationExpr) -> Result<(), LoxError> {
+        for part in &expr.parts {
+            self.resolve_expr(part);
+        }
+        Ok(())
+    }
+
+    fn visit_literal_expr(&self, _expr: &LiteralExpr) -> Result<(), LoxError> {
+        Ok(())
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.value);
+        self.resolve_expr(&expr.object);
+        Ok(())
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<(), LoxError> {
+        match self.current_class.get() {
+            ClassType::None => {
+                self.error(&expr.keyword, "Can't use 'super' outside of a class.");
+                return Ok(());
+            }
+            ClassType::Class => {
+                self.error(
+                    &expr.keyword,
+                    "Can't use 'super' in a class with no superclass.",
+                );
+                return Ok(());
+            }
+            ClassType::Subclass => {}
+        }
+
+        self.resolve_local(&expr.depth, &expr.keyword, true);
+        Ok(())
+    }
+
+    fn visit_this_expr(&self, expr: &ThisExpr) -> Result<(), LoxError> {
+        if self.current_class.get() == ClassType::None {
+            self.error(&expr.keyword, "Can't use 'this' outside of a class.");
+            return Ok(());
+        }
+
+        self.resolve_local(&expr.depth, &expr.keyword, true);
+        Ok(())
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<(), LoxError> {
+        let in_own_initializer = self
+            .scopes
+            .borrow()
+            .last()
+            .and_then(|scope| scope.get(&expr.name.lexeme))
+            .is_some_and(|binding| !binding.defined);
+        if in_own_initializer {
+            self.error(
+                &expr.name,
+                "Can't read local variable in its own initializer.",
+            );
+        }
+
+        self.resolve_local(&expr.depth, &expr.name, true);
+        Ok(())
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver {
+            scopes: RefCell::new(Vec::new()),
+            current_function: Cell::new(FunctionType::None),
+            current_class: Cell::new(ClassType::None),
+            diagnostics: RefCell::new(Vec::new()),
+        }
+    }
+
+    // Resolves a whole program, returning every error and warning found.
+    pub fn resolve_program(&self, statements: &[Stmt]) -> Vec<LoxError> {
+        self.resolve(statements);
+        self.diagnostics.take()
+    }
+
+    fn resolve(&self, statements: &[Stmt]) {
+        for statement in statements {
+            self.resolve_stmt(statement);
+        }
+    }
+
+    fn resolve_stmt(&self, stmt: &Stmt) {
+        // Errors are collected as diagnostics rather than returned, so the
+        // visitor results carry nothing.
+        let _ = stmt.accept(self);
+    }
+
+    fn resolve_expr(&self, expr: &Expr) {
+        let _ = expr.accept(self);
+    }
+
+    fn resolve_function(&self, function: &FunctionStmt, function_type: FunctionType) {
+        let enclosing_function = self.current_function.replace(function_type);
+
+        self.begin_scope();
+        for param in function.params.iter() {
+            self.declare(param, false);
+            self.define(param);
+        }
+        self.resolve(&function.body);
+        self.end_scope();
+
+        self.current_function.set(enclosing_function);
+    }
+
+    fn begin_scope(&self) {
+        self.scopes.borrow_mut().push(HashMap::new());
+    }
+
+    fn end_scope(&self) {
+        let Some(scope) = self.scopes.borrow_mut().pop() else {
+            return;
+        };
+
+        let mut unused: Vec<&Binding> = scope
+            .values()
+            .filter(|binding| binding.warn_if_unused && !binding.used)
+            .collect();
+        unused.sort_by_key(|binding| binding.name.span.line);
+        for binding in unused {
+            self.diagnostics.borrow_mut().push(
+                LoxError::warning(
+                    &binding.name,
+                    &format!("Local variable '{}' is never used.", binding.name.lexeme),
+                )
+                .with_help("remove the declaration if it isn't needed"),
+            );
+        }
+    }
+
+    fn declare(&self, name: &Token, warn_if_unused: bool) {
+        let mut scopes = self.scopes.borrow_mut();
+        let Some(scope) = scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&name.lexeme) {
+            self.error(name, "Already a variable with this name in this scope.");
+        }
+
+        scope.insert(
+            name.lexeme.clone(),
+            Binding {
+                name: name.clone(),
+                defined: false,
+                used: false,
+                warn_if_unused,
+            },
+        );
+    }
+
+    fn define(&self, name: &Token) {
+        if let Some(binding) = self
+            .scopes
+            .borrow_mut()
+            .last_mut()
+            .and_then(|scope| scope.get_mut(&name.lexeme))
+        {
+            binding.defined = true;
+        }
+    }
+
+    // Binds a name the language introduces on its own, such as `this`, in the
+    // innermost scope.
+    fn define_implicit(&self, name: &str) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(
+                name.to_string(),
+                Binding {
+                    name: Token::new(
+                        TokenType::Identifier,
+                        name.to_string(),
+                        None,
+                        Span::default(),
+                    ),
+                    defined: true,
+                    used: true,
+                    warn_if_unused: false,
+                },
+            );
+        }
+    }
+
+    fn resolve_local(&self, depth: &Cell<Option<usize>>, name: &Token, is_read: bool) {
+        let mut scopes = self.scopes.borrow_mut();
+        for (distance, scope) in scopes.iter_mut().rev().enumerate() {
+            if let Some(binding) = scope.get_mut(&name.lexeme) {
+                if is_read {
+                    binding.used = true;
+                }
+                depth.set(Some(distance));
+                return;
+            }
+        }
+        // Not found in any local scope: assume it is global.
+        depth.set(None);
+    }
+
+    fn error(&self, token: &Token, message: &str) {
+        self.diagnostics
+            .borrow_mut()
+            .push(LoxError::resolve_error(token, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+    use crate::value::Value;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let tokens = Scanner::new(source.to_string())
+            .scan_tokens()
+            .expect("source should scan")
+            .clone();
+        Parser::new(tokens).parse().expect("source should parse")
+    }
+
+    // Each diagnostic's message with the line it was reported on.
+    fn diagnostics(source: &str) -> Vec<(String, usize)> {
+        Resolver::new()
+            .resolve_program(&parse(source))
+            .iter()
+            .map(|err| (err.message().to_string(), err.details().line))
+            .collect()
+    }
+
+    // The depth resolved for each variable printed by a `print` statement,
+    // in source order, looking inside blocks and function bodies.
+    fn printed_depths(source: &str) -> Vec<(String, Option<usize>)> {
+        fn walk(statements: &[Stmt], depths: &mut Vec<(String, Option<usize>)>) {
+            for statement in statements {
+                match statement {
+                    Stmt::Block(block) => walk(&block.statements, depths),
+                    Stmt::Function(function) => walk(&function.body, depths),
+                    Stmt::Print(PrintStmt {
+                        expression: Expr::Variable(variable),
+                    }) => depths.push((variable.name.lexeme.clone(), variable.depth.get())),
+                    _ => {}
+                }
+            }
+        }
+
+        let statements = parse(source);
+        let diagnostics = Resolver::new().resolve_program(&statements);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let mut depths = Vec::new();
+        walk(&statements, &mut depths);
+        depths
+    }
+
+    #[test]
+    fn locals_resolve_to_the_scope_that_declares_them() {
+        let source = "var g = 0;
+            { var a = 1; print a;
+              { var b = 2; print a; print b; print g; }
+              fun f(p) { print p; print a; }
+              f(a);
+            }";
+        assert_eq!(
+            printed_depths(source),
+            [
+                ("a".to_string(), Some(0)),
+                ("a".to_string(), Some(1)),
+                ("b".to_string(), Some(0)),
+                ("g".to_string(), None),
+                ("p".to_string(), Some(0)),
+                ("a".to_string(), Some(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn closures_capture_the_variable_in_scope_where_they_are_declared() {
+        // `show` keeps reading the outer `a` even after a shadowing `a` is
+        // declared in the same block.
+        let source = "{ var a = 1; { fun show() { print a; } show(); var a = 2; print a; } }";
+        assert_eq!(
+            printed_depths(source),
+            [("a".to_string(), Some(2)), ("a".to_string(), Some(0))]
+        );
+    }
+
+    #[test]
+    fn reading_a_local_in_its_own_initializer_is_an_error() {
+        assert_eq!(
+            diagnostics("var a = 1;\n{\n  var a = a;\n}"),
+            [(
+                "Can't read local variable in its own initializer.".to_string(),
+                3
+            )]
+        );
+        // Globals may refer to themselves; the check is only for locals.
+        assert_eq!(diagnostics("var a = a;"), []);
+    }
+
+    #[test]
+    fn returning_from_top_level_code_is_an_error() {
+        assert_eq!(
+            diagnostics("print 1;\nreturn 2;"),
+            [("Can't return from top-level code.".to_string(), 2)]
+        );
+        assert_eq!(diagnostics("fun f() { return 2; } f();"), []);
+    }
+
+    #[test]
+    fn redeclaring_a_local_in_the_same_scope_is_an_error() {
+        assert_eq!(
+            diagnostics("{\n  var a = 1;\n  var a = 2;\n  print a;\n}"),
+            [(
+                "Already a variable with this name in this scope.".to_string(),
+                3
+            )]
+        );
+        assert_eq!(
+            diagnostics("fun f(a, a) {}"),
+            [(
+                "Already a variable with this name in this scope.".to_string(),
+                1
+            )]
+        );
+        // Shadowing in an inner scope and redeclaring globals are fine.
+        assert_eq!(
+            diagnostics("var a; var a; { var b = a; { var b = 2; print b; } print b; }"),
+            []
+        );
+    }
+
+    #[test]
+    fn unused_locals_are_warned_about() {
+        assert_eq!(
+            diagnostics("{\n  var used = 1;\n  var unused = 2;\n  print used;\n}"),
+            [("Local variable 'unused' is never used.".to_string(), 3)]
+        );
+        // Only locals: globals, parameters and functions are left alone.
+        assert_eq!(diagnostics("var global; fun f(p) { fun g() {} }"), []);
+    }
+
+    #[test]
+    fn warnings_do_not_stop_the_program() {
+        let statements = parse("var result;\n{\n  var unused = 1;\n  result = \"ran\";\n}");
+        let diagnostics = Resolver::new().resolve_program(&statements);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Warning);
+        assert!(!diagnostics[0].is_error());
+
+        let interpreter = Interpreter::new();
+        interpreter
+            .interpret(&statements)
+            .expect("program should run");
+        let result = interpreter.globals().borrow().get_here("result");
+        assert_eq!(result, Some(Value::from("ran".to_string())));
+    }
+}
@@ -1,14 +1,28 @@
 use crate::error::LoxError;
+use crate::span::Span;
 use crate::token::{Object, Token};
 use crate::token_types::TokenType;
 use std::collections::HashMap;
+
+// `start` and `current` are byte offsets into `source` and always sit on a
+// char boundary, so lexemes can be sliced out directly and each step of the
+// scan is constant time.
 pub struct Scanner {
     source: String,
     start: usize,
     current: usize,
     line: usize,
+    // 1-based column of `current`, counted in chars.
+    column: usize,
+    // Where the current lexeme starts.
+    start_line: usize,
+    start_column: usize,
     tokens: Vec<Token>,
     keywords: HashMap<String, TokenType>,
+    diagnostics: Vec<LoxError>,
+    // One entry per `${` whose expression is still being scanned: the
+    // number of unclosed '{' inside it, and where the `${` is.
+    interpolations: Vec<(usize, Span)>,
 }
 impl Scanner {
     pub fn new(source: String) -> Self {
@@ -17,6 +31,9 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_line: 1,
+            start_column: 1,
             tokens: Vec::new(),
             keywords: HashMap::from([
                 ("and".to_string(), TokenType::And),
@@ -36,6 +53,8 @@ impl Scanner {
                 ("var".to_string(), TokenType::Var),
                 ("while".to_string(), TokenType::While),
             ]),
+            diagnostics: Vec::new(),
+            interpolations: Vec::new(),
         }
     }
 
@@ -47,23 +66,45 @@ impl Scanner {
         self.current
     }
 
+    fn lexeme_span(&self) -> Span {
+        Span::new(self.start, self.current, self.start_line, self.start_column)
+    }
+
     // SETTERS
     fn set_start(&mut self, index: usize) {
-        self.start = index
+        self.start = index;
+        self.start_line = self.line;
+        self.start_column = self.column;
+    }
+
+    // Call after consuming a '\n'.
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.column = 1;
+    }
+
+    // Records a lexical error and lets scanning carry on, so one bad
+    // character doesn't hide the ones after it.
+    fn error(&mut self, span: Span, message: &str) {
+        self.diagnostics.push(LoxError::scan_error(span, message));
     }
 
     fn matches(&mut self, expected: char) -> bool {
-        if self.is_at_end() || (self.source.chars().nth(self.current) != Some(expected)) {
+        if self.is_at_end() || self.peek() != expected {
             false
         } else {
-            self.current += 1;
+            self.current += expected.len_utf8();
+            self.column += 1;
             true
         }
     }
 
     fn advance(&mut self) -> Option<char> {
-        let result = self.source.chars().nth(self.current);
-        self.current += 1;
+        let result = self.source[self.current..].chars().next();
+        if let Some(c) = result {
+            self.current += c.len_utf8();
+            self.column += 1;
+        }
         result
     }
     fn add_token(&mut self, token_type: TokenType) {
@@ -71,9 +112,7 @@ impl Scanner {
     }
 
     fn add_token_object(&mut self, token_type: TokenType, literal: Option<Object>) {
-        let text = self
-            .source
-            .get(self.start.try_into().unwrap()..self.current.try_into().unwrap());
+        let text = self.source.get(self.start..self.current);
         let token = Token {
             token_type,
             literal,
@@ -81,7 +120,7 @@ impl Scanner {
                 None => String::from(""),
                 Some(value) => value.to_string(),
             },
-            line: self.line,
+            span: self.lexeme_span(),
         };
         self.tokens.push(token)
     }
@@ -95,66 +134,251 @@ impl Scanner {
     }
 
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current).unwrap()
-        }
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
-    fn handle_string_literal(&mut self) {
+    // The token's lexeme keeps the literal as written; its value has the
+    // escape sequences decoded.
+    //
+    // A string containing `${expr}` is scanned as an `Interpolation` token
+    // for each segment ending in `${`, the tokens of each expression, and a
+    // final `InterpolationEnd` token that starts at the `}` closing the last
+    // one. `after_interpolation` is set when scanning resumes at that `}`.
+    fn handle_string_literal(&mut self, after_interpolation: bool) {
+        let mut value = String::new();
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1
-            };
-            self.advance();
+            if self.peek() == '$' && self.peek_next() == '{' {
+                let opener = Span::new(self.current, self.current + 2, self.line, self.column);
+                self.advance();
+                self.advance();
+                self.interpolations.push((0, opener));
+                self.add_token_object(TokenType::Interpolation, Some(Object::Str(value)));
+                return;
+            }
+            match self.advance() {
+                Some('\\') => {
+                    if let Some(c) = self.escape_sequence() {
+                        value.push(c);
+                    }
+                }
+                Some(c) => {
+                    if c == '\n' {
+                        self.new_line();
+                    }
+                    value.push(c);
+                }
+                None => {}
+            }
         }
 
         if self.is_at_end() {
-            panic!("Unterminated String");
+            self.diagnostics.push(
+                LoxError::scan_error(self.lexeme_span(), "Unterminated string.")
+                    .with_help("add a closing '\"' to end the string"),
+            );
+            // Any `${` still open was swallowed by this string, and saying
+            // so again would only repeat the error.
+            self.interpolations.clear();
+            return;
         }
 
         // The closing ".
         self.advance();
 
-        // Trim the surrounding quotes.
-        let value = self.source.get(self.start + 1..self.current - 1).unwrap();
-        self.add_token_object(TokenType::String, Some(Object::Str(value.to_string())));
+        let token_type = if after_interpolation {
+            TokenType::InterpolationEnd
+        } else {
+            TokenType::String
+        };
+        self.add_token_object(token_type, Some(Object::Str(value)));
     }
 
-    fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current + 1).unwrap()
+    // Decodes the escape sequence after a '\' that has just been consumed.
+    // Invalid escapes are reported and decode to nothing.
+    fn escape_sequence(&mut self) -> Option<char> {
+        let start = self.current - 1;
+        let (line, column) = (self.line, self.column - 1);
+        let span = move |scanner: &Self| Span::new(start, scanner.current, line, column);
+
+        let decoded = match self.advance() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('$') => '$',
+            Some('u') => return self.unicode_escape(span),
+            Some(c) => {
+                if c == '\n' {
+                    self.new_line();
+                }
+                self.diagnostics.push(
+                    LoxError::scan_error(
+                        span(self),
+                        &format!("Invalid escape sequence '\\{}'.", c.escape_default()),
+                    )
+                    .with_help(
+                        "valid escapes are \\n, \\t, \\r, \\0, \\\\, \\\", \\$ and \\u{...}",
+                    ),
+                );
+                return None;
+            }
+            None => return None,
+        };
+        Some(decoded)
+    }
+
+    // `\u{1F600}`: one to six hex digits naming a Unicode scalar value.
+    fn unicode_escape(&mut self, span: impl Fn(&Self) -> Span) -> Option<char> {
+        if !self.matches('{') {
+            self.error(span(self), "Expect '{' after '\\u'.");
+            return None;
+        }
+        let digits_start = self.current;
+        while self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let digits = self.source[digits_start..self.current].to_string();
+        if !self.matches('}') {
+            self.error(span(self), "Expect '}' to close the unicode escape.");
+            return None;
+        }
+        if digits.is_empty() || digits.len() > 6 {
+            self.error(span(self), "Unicode escape must have 1 to 6 hex digits.");
+            return None;
+        }
+        let decoded = u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32);
+        if decoded.is_none() {
+            let message = format!("'{}' is not a valid unicode code point.", digits);
+            self.error(span(self), &message);
         }
+        decoded
+    }
+
+    fn peek_next(&self) -> char {
+        let mut chars = self.source[self.current..].chars();
+        chars.next();
+        chars.next().unwrap_or('\0')
     }
 
     fn is_digit(&self, c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
+    // Decimal literals may have a fraction and an exponent (`2.5e-3`);
+    // `0x`, `0b` and `0o` prefixes select hexadecimal, binary and octal.
+    // Underscores may separate digits anywhere in the literal.
     fn number(&mut self) {
-        while self.is_digit(self.peek()) {
-            self.advance();
+        let first = self.source[self.start..].chars().next();
+        let radix = match (first, self.peek()) {
+            (Some('0'), 'x' | 'X') => Some((16, "hexadecimal")),
+            (Some('0'), 'b' | 'B') => Some((2, "binary")),
+            (Some('0'), 'o' | 'O') => Some((8, "octal")),
+            _ => None,
+        };
+        let value = match radix {
+            Some((radix, name)) => self.radix_number(radix, name),
+            None => self.decimal_number(),
+        };
+        if let Some(value) = value {
+            self.add_token_object(TokenType::Number, Some(Object::Num(value)));
         }
+    }
+
+    fn decimal_number(&mut self) -> Option<f64> {
+        self.digits(10);
         // Look for a fractional part.
         if self.peek() == '.' && self.is_digit(self.peek_next()) {
             // Consume the "."
             self.advance();
-
-            while self.is_digit(self.peek()) {
+            self.digits(10);
+        }
+        if matches!(self.peek(), 'e' | 'E') {
+            self.advance();
+            if matches!(self.peek(), '+' | '-') {
                 self.advance();
             }
+            if !self.is_digit(self.peek()) {
+                self.error(self.lexeme_span(), "Expect digits in exponent.");
+                return None;
+            }
+            self.digits(10);
+        }
+
+        let text = self.checked_separators(10)?;
+        match text.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.error(self.lexeme_span(), "Invalid number literal.");
+                None
+            }
+        }
+    }
+
+    fn radix_number(&mut self, radix: u32, name: &str) -> Option<f64> {
+        // Consume the prefix letter.
+        self.advance();
+        self.digits(radix);
+        // Letters and digits running on from the literal are a typo in it,
+        // like `0b102` or `0xFG`, rather than the start of another token.
+        let invalid = self.is_alpha_numeric(self.peek()).then(|| self.peek());
+        while self.is_alpha_numeric(self.peek()) {
+            self.advance();
+        }
+        if let Some(c) = invalid {
+            self.error(
+                self.lexeme_span(),
+                &format!("Invalid digit '{}' in {} literal.", c, name),
+            );
+            return None;
+        }
+
+        let text = self.checked_separators(radix)?;
+        let digits = &text[2..];
+        if digits.is_empty() {
+            let prefix = &self.source[self.start..self.start + 2];
+            self.error(
+                self.lexeme_span(),
+                &format!("Expect {} digits after '{}'.", name, prefix),
+            );
+            return None;
+        }
+        // Accumulating in f64 means literals too big for an integer type
+        // lose precision instead of failing, like large decimal literals.
+        Some(digits.chars().fold(0.0, |value, digit| {
+            value * radix as f64 + digit.to_digit(radix).unwrap() as f64
+        }))
+    }
+
+    // Consumes a run of digits in `radix` and `_` separators.
+    fn digits(&mut self, radix: u32) {
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            self.advance();
         }
-        let num_val: f64 = self
-            .source
-            .get(self.start..self.current)
-            .unwrap()
-            .parse()
-            .unwrap();
+    }
 
-        self.add_token_object(TokenType::Number, Some(Object::Num(num_val)));
+    // The current lexeme with its separators removed, or None after reporting
+    // a separator that doesn't sit between two digits.
+    fn checked_separators(&mut self, radix: u32) -> Option<String> {
+        let text = &self.source[self.start..self.current];
+        let chars: Vec<char> = text.chars().collect();
+        let misplaced = chars.iter().enumerate().any(|(i, &c)| {
+            c == '_'
+                && !(i > 0
+                    && chars[i - 1].is_digit(radix)
+                    && chars.get(i + 1).is_some_and(|next| next.is_digit(radix)))
+        });
+        if misplaced {
+            self.error(
+                self.lexeme_span(),
+                "Digit separators must be between two digits.",
+            );
+            return None;
+        }
+        Some(text.replace('_', ""))
     }
 
     fn is_alpha(&self, c: char) -> bool {
@@ -162,7 +386,7 @@ impl Scanner {
     }
 
     fn is_alpha_numeric(&self, c: char) -> bool {
-        c.is_ascii_alphanumeric() || c.is_ascii_digit()
+        c.is_ascii_alphanumeric() || c == '_'
     }
 
     fn identifier(&mut self) {
@@ -178,33 +402,41 @@ impl Scanner {
         }
     }
 
-    fn handle_multiline_comment(&mut self) {
-        loop {
+    // Block comments nest. Returns false if the input ends before the
+    // comment is closed, after reporting it once at the outermost opener.
+    fn handle_multiline_comment(&mut self) -> bool {
+        // The opening "/*" has already been consumed.
+        let (start, line, column) = (self.current - 2, self.line, self.column - 2);
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                let span = Span::new(start, self.current, line, column);
+                self.error(span, "Unterminated block comment.");
+                return false;
+            }
             match self.peek() {
                 '*' => {
                     self.advance();
                     if self.matches('/') {
-                        return;
+                        depth -= 1;
                     }
                 }
                 '/' => {
                     self.advance();
                     if self.matches('*') {
-                        self.handle_multiline_comment();
+                        depth += 1;
                     }
                 }
                 '\n' => {
-                    self.line += 1;
                     self.advance();
+                    self.new_line();
                 }
                 _ => {
                     self.advance();
                 }
             }
-            if self.is_at_end() {
-                panic!("Failed to close open comment")
-            }
         }
+        true
     }
 
     fn scan_token(&mut self) {
@@ -213,13 +445,31 @@ impl Scanner {
             match character {
                 '(' => self.add_token(TokenType::LeftParen),
                 ')' => self.add_token(TokenType::RightParen),
-                '{' => self.add_token(TokenType::LeftBrace),
-                '}' => self.add_token(TokenType::RightBrace),
+                '{' => {
+                    if let Some((depth, _)) = self.interpolations.last_mut() {
+                        *depth += 1;
+                    }
+                    self.add_token(TokenType::LeftBrace)
+                }
+                '}' => match self.interpolations.last_mut() {
+                    Some((0, _)) => {
+                        // Closes a `${`, so the enclosing string carries on.
+                        self.interpolations.pop();
+                        self.handle_string_literal(true);
+                    }
+                    Some((depth, _)) => {
+                        *depth -= 1;
+                        self.add_token(TokenType::RightBrace)
+                    }
+                    None => self.add_token(TokenType::RightBrace),
+                },
                 ',' => self.add_token(TokenType::Comma),
                 '.' => self.add_token(TokenType::Dot),
                 '-' => self.add_token(TokenType::Minus),
                 '+' => self.add_token(TokenType::Plus),
                 ';' => self.add_token(TokenType::Semicolon),
+                '?' => self.add_token(TokenType::Question),
+                ':' => self.add_token(TokenType::Colon),
                 '*' => self.add_token(TokenType::Star),
                 '!' => self.match_and_advance('=', TokenType::BangEqual, TokenType::Bang),
                 '=' => self.match_and_advance('=', TokenType::EqualEqual, TokenType::Equal),
@@ -237,15 +487,18 @@ impl Scanner {
                     }
                 }
                 ' ' | '\r' | '\t' => {}
-                '\n' => self.line += 1,
-                '"' => self.handle_string_literal(),
+                '\n' => self.new_line(),
+                '"' => self.handle_string_literal(false),
                 _token => {
                     if self.is_digit(_token) {
                         self.number()
                     } else if self.is_alpha(_token) {
                         self.identifier()
                     } else {
-                        unreachable!("token type not matched")
+                        self.error(
+                            self.lexeme_span(),
+                            &format!("Unexpected character '{}'.", _token),
+                        );
                     }
                 }
             }
@@ -253,24 +506,308 @@ impl Scanner {
     }
 
     fn is_at_end(&self) -> bool {
-        return self.current() >= self.source.len();
+        self.current() >= self.source.len()
     }
 
-    pub fn scan_tokens(&mut self) -> Result<&Vec<Token>, LoxError> {
+    // Scans the whole source. Lexical errors don't stop the scan; they are
+    // collected and all returned together in place of the tokens.
+    pub fn scan_tokens(&mut self) -> Result<&Vec<Token>, Vec<LoxError>> {
         while !self.is_at_end() {
             // We are at the beginning of the next lexeme.
             self.set_start(self.current());
             self.scan_token();
         }
+        for (_, opener) in std::mem::take(&mut self.interpolations) {
+            self.diagnostics.push(
+                LoxError::scan_error(opener, "Unterminated string interpolation.")
+                    .with_help("add a '}' to end the interpolated expression"),
+            );
+        }
         self.tokens.push(Token::new(
             TokenType::Eof,
             String::from(""),
             None,
-            self.line(),
+            Span::new(
+                self.source.len(),
+                self.source.len(),
+                self.line(),
+                self.column,
+            ),
         ));
-        dbg!("{}", &self.tokens);
-        println!("{}", self.tokens.len());
-
+        if !self.diagnostics.is_empty() {
+            return Err(std::mem::take(&mut self.diagnostics));
+        }
         Ok(&self.tokens)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(source: &str) -> f64 {
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = scanner.scan_tokens().expect("source should scan");
+        assert_eq!(tokens.len(), 2, "{source} should scan as one token");
+        match tokens[0].literal {
+            Some(Object::Num(value)) => value,
+            ref other => panic!("{source} scanned as {other:?}"),
+        }
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        let mut scanner = Scanner::new(source.to_string());
+        let Err(errors) = scanner.scan_tokens() else {
+            panic!("{source} should not scan");
+        };
+        errors.iter().map(|err| err.message().to_string()).collect()
+    }
+
+    // Each error's message with the line and column it was reported at.
+    fn located_errors(source: &str) -> Vec<(String, usize, usize)> {
+        let mut scanner = Scanner::new(source.to_string());
+        let Err(errors) = scanner.scan_tokens() else {
+            panic!("{source} should not scan");
+        };
+        errors
+            .iter()
+            .map(|err| {
+                let span = err.details().span.expect("scan errors have a span");
+                (err.message().to_string(), span.line, span.column)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_error_in_a_scan_is_collected() {
+        assert_eq!(
+            located_errors("var a = @;\nvar b = #;\nprint \"oops;"),
+            [
+                ("Unexpected character '@'.".to_string(), 1, 9),
+                ("Unexpected character '#'.".to_string(), 2, 9),
+                ("Unterminated string.".to_string(), 3, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_literals_are_reported_where_they_start() {
+        assert_eq!(
+            located_errors("print 1;\n  \"never\nclosed"),
+            [("Unterminated string.".to_string(), 2, 3)]
+        );
+        assert_eq!(
+            located_errors("print 1; /* open\n*/ /*\n still open"),
+            [("Unterminated block comment.".to_string(), 2, 4)]
+        );
+        assert_eq!(
+            located_errors("/* outer /* inner */ still outer"),
+            [("Unterminated block comment.".to_string(), 1, 1)]
+        );
+        assert_eq!(
+            located_errors("/* outer /* inner"),
+            [("Unterminated block comment.".to_string(), 1, 1)]
+        );
+    }
+
+    // Each token's lexeme with the line and column of its span.
+    fn located_tokens(source: &str) -> Vec<(String, usize, usize)> {
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = scanner.scan_tokens().expect("source should scan");
+        tokens
+            .iter()
+            .map(|token| (token.lexeme.clone(), token.span.line, token.span.column))
+            .collect()
+    }
+
+    #[test]
+    fn tokens_know_their_line_and_column() {
+        let located = located_tokens(
+            "var a =
+  a + 1;",
+        );
+        let expected = [
+            ("var", 1, 1),
+            ("a", 1, 5),
+            ("=", 1, 7),
+            ("a", 2, 3),
+            ("+", 2, 5),
+            ("1", 2, 7),
+            (";", 2, 8),
+            ("", 2, 9),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|&(lexeme, line, column)| (lexeme.to_string(), line, column))
+            .collect();
+        assert_eq!(located, expected);
+    }
+
+    #[test]
+    fn columns_count_chars_and_spans_count_bytes() {
+        // "é" is two bytes and "😀" four, but each is one column.
+        let source = "\"é\" + \"😀\" + x;";
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = scanner.scan_tokens().expect("source should scan");
+        let spans: Vec<_> = tokens
+            .iter()
+            .map(|token| (token.span.column, token.span.len()))
+            .collect();
+        assert_eq!(
+            spans,
+            [(1, 4), (5, 1), (7, 6), (11, 1), (13, 1), (14, 1), (15, 0)]
+        );
+        assert_eq!(&source[tokens[4].span.start..tokens[4].span.end], "x");
+    }
+
+    #[test]
+    fn no_input_panics() {
+        let fragments = [
+            "var s = \"a ${b + \"c\"} d\";",
+            "print 0x1F + 0b10 + 0o7 + 1_000.5e-3;",
+            "/* a /* b */ c */ // d",
+            "\"\\u{1F600}\\n\\q\\\"\"",
+            "héllo → \"wörld\" 😀 {}",
+            "0x 0b2 1e 1__0 ${ } $",
+        ];
+        for fragment in fragments {
+            for (end, _) in fragment.char_indices() {
+                let _ = Scanner::new(fragment[..end].to_string()).scan_tokens();
+            }
+            let _ = Scanner::new(fragment.to_string()).scan_tokens();
+        }
+        for byte in 0..=127u8 {
+            let _ = Scanner::new(char::from(byte).to_string()).scan_tokens();
+        }
+    }
+
+    #[test]
+    fn decimal_literals() {
+        assert_eq!(number("123"), 123.0);
+        assert_eq!(number("1.5"), 1.5);
+        assert_eq!(number("1e10"), 1e10);
+        assert_eq!(number("2.5E-3"), 2.5e-3);
+        assert_eq!(number("4e+2"), 400.0);
+        assert_eq!(number("1_000_000"), 1_000_000.0);
+        assert_eq!(number("12_345.678_9"), 12_345.678_9);
+    }
+
+    #[test]
+    fn prefixed_literals() {
+        assert_eq!(number("0xFF"), 255.0);
+        assert_eq!(number("0Xff"), 255.0);
+        assert_eq!(number("0b1010"), 10.0);
+        assert_eq!(number("0o17"), 15.0);
+        assert_eq!(number("0xdead_beef"), 3_735_928_559.0);
+        assert_eq!(number("0x1_F"), 31.0);
+        assert_eq!(number("0x1_0000_0000_0000_0000"), 2f64.powi(64));
+    }
+
+    #[test]
+    fn identifiers_may_contain_underscores() {
+        let mut scanner = Scanner::new("var my_var = _x1 + __;".to_string());
+        let tokens = scanner.scan_tokens().expect("source should scan");
+        let identifiers: Vec<&str> = tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::Identifier)
+            .map(|token| token.lexeme.as_str())
+            .collect();
+        assert_eq!(identifiers, ["my_var", "_x1", "__"]);
+    }
+
+    #[test]
+    fn malformed_literals_are_reported() {
+        assert_eq!(errors("0x"), ["Expect hexadecimal digits after '0x'."]);
+        assert_eq!(errors("0b;"), ["Expect binary digits after '0b'."]);
+        assert_eq!(errors("1e"), ["Expect digits in exponent."]);
+        assert_eq!(errors("1e+;"), ["Expect digits in exponent."]);
+        assert_eq!(errors("0b102"), ["Invalid digit '2' in binary literal."]);
+        assert_eq!(
+            errors("0xFG"),
+            ["Invalid digit 'G' in hexadecimal literal."]
+        );
+        for source in ["1_", "1__0", "0x_1", "0x1_", "0b1__0", "1_.5", "1_e5"] {
+            assert_eq!(
+                errors(source),
+                ["Digit separators must be between two digits."],
+                "{source}"
+            );
+        }
+    }
+
+    fn string(source: &str) -> (String, String) {
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = scanner.scan_tokens().expect("source should scan");
+        match &tokens[0].literal {
+            Some(Object::Str(value)) => (tokens[0].lexeme.clone(), value.clone()),
+            other => panic!("{source} scanned as {other:?}"),
+        }
+    }
+
+    #[test]
+    fn escape_sequences_are_decoded() {
+        let source = r#""a\tb\nc\r\\ \"q\" \0""#;
+        assert_eq!(
+            string(source),
+            (source.to_string(), "a\tb\nc\r\\ \"q\" \0".to_string())
+        );
+        assert_eq!(string(r#""\u{1F600}\u{e9}""#).1, "\u{1F600}\u{e9}");
+    }
+
+    #[test]
+    fn invalid_escapes_are_reported_at_their_column() {
+        let mut scanner = Scanner::new(r#"var s = "ab\q";"#.to_string());
+        let scanned = scanner.scan_tokens().unwrap_err();
+        assert_eq!(scanned.len(), 1);
+        assert_eq!(scanned[0].message(), "Invalid escape sequence '\\q'.");
+        let span = scanned[0].details().span.unwrap();
+        assert_eq!((span.line, span.column, span.len()), (1, 12, 2));
+
+        assert_eq!(errors(r#""\u0041""#), ["Expect '{' after '\\u'."]);
+        assert_eq!(
+            errors(r#""\u{}""#),
+            ["Unicode escape must have 1 to 6 hex digits."]
+        );
+        assert_eq!(
+            errors(r#""\u{1234567}""#),
+            ["Unicode escape must have 1 to 6 hex digits."]
+        );
+        assert_eq!(
+            errors(r#""\u{D800}""#),
+            ["'D800' is not a valid unicode code point."]
+        );
+        assert_eq!(
+            errors(r#""\u{41""#),
+            ["Expect '}' to close the unicode escape."]
+        );
+    }
+
+    #[test]
+    fn interpolated_strings_are_split_into_segments() {
+        let mut scanner = Scanner::new(r#""a ${ "b${c}" } d""#.to_string());
+        let tokens = scanner.scan_tokens().expect("source should scan");
+        let scanned: Vec<(TokenType, &str)> = tokens
+            .iter()
+            .map(|token| (token.token_type.clone(), token.lexeme.as_str()))
+            .collect();
+        assert_eq!(
+            scanned,
+            [
+                (TokenType::Interpolation, r#""a ${"#),
+                (TokenType::Interpolation, r#""b${"#),
+                (TokenType::Identifier, "c"),
+                (TokenType::InterpolationEnd, r#"}""#),
+                (TokenType::InterpolationEnd, r#"} d""#),
+                (TokenType::Eof, ""),
+            ]
+        );
+        assert_eq!(tokens[0].literal, Some(Object::Str("a ".to_string())));
+        assert_eq!(tokens[4].literal, Some(Object::Str(" d".to_string())));
+    }
+
+    #[test]
+    fn unterminated_interpolation_is_reported() {
+        assert_eq!(errors(r#""a ${b"#), ["Unterminated string interpolation."]);
+        assert_eq!(string(r#""\${b}""#).1, "${b}");
+    }
+}
@@ -0,0 +1,3 @@
+// Generated at build time from the `[Stmt]` section of src/ast.spec. A copy
+// of the output is kept in src/generated/stmt.rs.
+include!(concat!(env!("OUT_DIR"), "/stmt.rs"));
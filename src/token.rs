@@ -1,6 +1,11 @@
+use crate::span::Span;
 use crate::token_types::TokenType;
+use crate::value::format_number;
 use std::fmt;
-#[derive(Debug)]
+
+// A literal value as written in the source. The interpreter turns these into
+// runtime `Value`s.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Object {
     Num(f64),
     Str(String),
@@ -13,28 +18,29 @@ impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Object::Str(val) => write!(f, "{}", val),
-            Object::Num(val) => write!(f, "{}", val),
-            Object::Nil => write!(f, "Nil"),
-            Object::True => write!(f, "True"),
-            Object::False => write!(f, "False"),
+            Object::Num(val) => write!(f, "{}", format_number(*val)),
+            Object::Nil => write!(f, "nil"),
+            Object::True => write!(f, "true"),
+            Object::False => write!(f, "false"),
         }
     }
 }
-#[derive(Debug)]
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<Object>,
-    pub line: usize,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: Option<Object>, line: usize) -> Self {
+    pub fn new(token_type: TokenType, lexeme: String, literal: Option<Object>, span: Span) -> Self {
         Self {
             token_type,
             lexeme,
             literal,
-            line,
+            span,
         }
     }
 }
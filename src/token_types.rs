@@ -11,6 +11,8 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Question,
+    Colon,
     Bang,
     BangEqual,
     Equal,
@@ -21,6 +23,11 @@ pub enum TokenType {
     LessEqual,
     Identifier,
     String,
+    // A segment of a string literal that ends at `${`.
+    Interpolation,
+    // The last segment of an interpolated string, from the `}` closing its
+    // last expression to the closing `"`.
+    InterpolationEnd,
     Number,
     And,
     Class,
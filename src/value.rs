@@ -0,0 +1,257 @@
+use crate::callable::Callable;
+use crate::lox_class::LoxClass;
+use crate::lox_instance::LoxInstance;
+use crate::token::Object;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+
+// Every string value alive in the interpreter, so each distinct text is
+// allocated once and strings compare by pointer. Entries are weak, bucketed
+// by a hash of the text, so a string is freed once no value holds it.
+#[derive(Default)]
+struct Strings {
+    buckets: HashMap<u64, Vec<Weak<str>>>,
+    // Entries across all buckets, dead or alive, and how many there may be
+    // before the whole table is swept for dead ones.
+    entries: usize,
+    next_sweep: usize,
+}
+
+impl Strings {
+    const MIN_SWEEP: usize = 256;
+
+    fn intern(&mut self, text: &str) -> Rc<str> {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let bucket = self.buckets.entry(hasher.finish()).or_default();
+        let before = bucket.len();
+        bucket.retain(|entry| entry.strong_count() > 0);
+        self.entries -= before - bucket.len();
+        if let Some(existing) = bucket
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|existing| &**existing == text)
+        {
+            return existing;
+        }
+
+        let string: Rc<str> = Rc::from(text);
+        bucket.push(Rc::downgrade(&string));
+        self.entries += 1;
+        if self.entries > self.next_sweep {
+            self.sweep();
+        }
+        string
+    }
+
+    // Drops dead entries everywhere. A dead `Weak` still pins its
+    // allocation, so buckets that are never looked up again must not keep
+    // them forever.
+    fn sweep(&mut self) {
+        self.buckets.retain(|_, bucket| {
+            bucket.retain(|entry| entry.strong_count() > 0);
+            !bucket.is_empty()
+        });
+        self.entries = self.buckets.values().map(Vec::len).sum();
+        self.next_sweep = (self.entries * 2).max(Self::MIN_SWEEP);
+    }
+}
+
+thread_local! {
+    static STRINGS: RefCell<Strings> = RefCell::new(Strings::default());
+}
+
+// A string held by a `Value`. The field is private so the only way to make
+// one is `Value::intern`, which keeps equal strings in one allocation; that
+// is what lets `PartialEq` compare them by pointer.
+#[derive(Debug, Clone)]
+pub struct LoxString(Rc<str>);
+
+impl PartialEq for LoxString {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for LoxString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// A value at run time in the tree-walking interpreter. Literals produced by
+// the scanner are `Object`s and become values through `From<Object>`.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Str(LoxString),
+    Func(Callable),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+}
+
+impl Value {
+    // Returns the shared copy of `text`, adding it to the table first if no
+    // live value holds it. Every `Value::Str` is made here.
+    pub fn intern(text: &str) -> Value {
+        Value::Str(LoxString(
+            STRINGS.with(|strings| strings.borrow_mut().intern(text)),
+        ))
+    }
+}
+
+// Lox equality: numbers, strings and booleans compare by value, functions,
+// classes and instances by identity. Strings are interned, so `LoxString`
+// only has to compare pointers.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Func(a), Value::Func(b)) => a == b,
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Number(value) => write!(f, "{}", format_number(*value)),
+            Value::Str(value) => write!(f, "{}", value),
+            Value::Func(value) => write!(f, "{}", value),
+            Value::Class(value) => write!(f, "{}", value),
+            Value::Instance(value) => write!(f, "{}", value.borrow()),
+        }
+    }
+}
+
+// Numbers print the way Lox prints them: integers without a trailing `.0`,
+// and infinities spelled out.
+pub fn format_number(value: f64) -> String {
+    if value.is_infinite() {
+        if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+impl From<Object> for Value {
+    fn from(literal: Object) -> Self {
+        match literal {
+            Object::Num(value) => Value::Number(value),
+            Object::Str(value) => Value::intern(&value),
+            Object::Nil => Value::Nil,
+            Object::True => Value::Bool(true),
+            Object::False => Value::Bool(false),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::intern(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_print_like_lox() {
+        assert_eq!(format_number(3.0), "3");
+        assert_eq!(format_number(2.5), "2.5");
+        assert_eq!(format_number(-0.5), "-0.5");
+        assert_eq!(format_number(-0.0), "-0");
+        assert_eq!(format_number(1e21), "1000000000000000000000");
+        assert_eq!(format_number(f64::INFINITY), "Infinity");
+        assert_eq!(format_number(f64::NEG_INFINITY), "-Infinity");
+        assert_eq!(format_number(f64::NAN), "NaN");
+    }
+
+    #[test]
+    fn values_display_like_lox() {
+        assert_eq!(Value::Nil.to_string(), "nil");
+        assert_eq!(Value::Bool(true).to_string(), "true");
+        assert_eq!(Value::Bool(false).to_string(), "false");
+        assert_eq!(Value::Number(3.0).to_string(), "3");
+        assert_eq!(Value::Number(2.5).to_string(), "2.5");
+        assert_eq!(Value::Number(1.0 / 0.0).to_string(), "Infinity");
+        assert_eq!(Value::intern("text").to_string(), "text");
+    }
+
+    fn string(value: &Value) -> &Rc<str> {
+        match value {
+            Value::Str(string) => &string.0,
+            other => panic!("{other:?} is not a string"),
+        }
+    }
+
+    #[test]
+    fn equal_strings_share_one_allocation() {
+        let literal = Value::from(Object::Str("ab".to_string()));
+        let built = Value::from(format!("{}{}", "a", "b"));
+        assert!(Rc::ptr_eq(string(&literal), string(&built)));
+        assert_eq!(literal, built);
+        assert_ne!(literal, Value::intern("ba"));
+    }
+
+    #[test]
+    fn the_table_does_not_keep_strings_alive() {
+        let value = Value::intern("only held here");
+        let weak = Rc::downgrade(string(&value));
+        drop(value);
+        assert!(weak.upgrade().is_none());
+
+        let value = Value::intern("only held here");
+        assert_eq!(Rc::strong_count(string(&value)), 1);
+    }
+
+    #[test]
+    fn sweeping_forgets_dead_strings() {
+        for i in 0..10 * Strings::MIN_SWEEP {
+            Value::intern(&format!("temporary {i}"));
+        }
+        let kept = Value::intern("kept");
+        STRINGS.with(|strings| {
+            let mut strings = strings.borrow_mut();
+            strings.sweep();
+            assert_eq!(strings.entries, 1);
+        });
+        assert_eq!(kept, Value::from("kept".to_string()));
+    }
+}
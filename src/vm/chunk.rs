@@ -0,0 +1,167 @@
+use crate::vm::value::Value;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Operands that index the constant pool are two bytes, big-endian, like jump
+// offsets. Stack slots, upvalue indices and counts are a single byte.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum OpCode {
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    GetLocal,
+    SetLocal,
+    GetGlobal,
+    DefineGlobal,
+    SetGlobal,
+    GetUpvalue,
+    SetUpvalue,
+    Equal,
+    Greater,
+    Less,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    // Operand: the number of values to pop, stringify and join.
+    Interpolate,
+    Print,
+    Jump,
+    JumpIfFalse,
+    Loop,
+    Call,
+    Closure,
+    CloseUpvalue,
+    Class,
+    GetProperty,
+    SetProperty,
+    Method,
+    Inherit,
+    GetSuper,
+    Return,
+}
+
+impl TryFrom<u8> for OpCode {
+    type Error = u8;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        const OPCODES: [OpCode; 36] = [
+            OpCode::Constant,
+            OpCode::Nil,
+            OpCode::True,
+            OpCode::False,
+            OpCode::Pop,
+            OpCode::GetLocal,
+            OpCode::SetLocal,
+            OpCode::GetGlobal,
+            OpCode::DefineGlobal,
+            OpCode::SetGlobal,
+            OpCode::GetUpvalue,
+            OpCode::SetUpvalue,
+            OpCode::Equal,
+            OpCode::Greater,
+            OpCode::Less,
+            OpCode::Add,
+            OpCode::Subtract,
+            OpCode::Multiply,
+            OpCode::Divide,
+            OpCode::Not,
+            OpCode::Negate,
+            OpCode::Interpolate,
+            OpCode::Print,
+            OpCode::Jump,
+            OpCode::JumpIfFalse,
+            OpCode::Loop,
+            OpCode::Call,
+            OpCode::Closure,
+            OpCode::CloseUpvalue,
+            OpCode::Class,
+            OpCode::GetProperty,
+            OpCode::SetProperty,
+            OpCode::Method,
+            OpCode::Inherit,
+            OpCode::GetSuper,
+            OpCode::Return,
+        ];
+        OPCODES.get(byte as usize).copied().ok_or(byte)
+    }
+}
+
+// A run of consecutive bytes that all came from the same source line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LineRun {
+    line: usize,
+    count: usize,
+}
+
+// Constants that are worth sharing when the same one is added again.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Str(Rc<str>),
+    // By bit pattern, so `0` and `-0` stay apart.
+    Number(u64),
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Value>,
+    // Run-length encoded, since most lines compile to several bytes.
+    lines: Vec<LineRun>,
+    // Where each string and number already in `constants` is.
+    constant_index: HashMap<ConstantKey, usize>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, byte: u8, line: usize) {
+        self.code.push(byte);
+        match self.lines.last_mut() {
+            Some(run) if run.line == line => run.count += 1,
+            _ => self.lines.push(LineRun { line, count: 1 }),
+        }
+    }
+
+    pub fn write_op(&mut self, op: OpCode, line: usize) {
+        self.write(op as u8, line)
+    }
+
+    // Returns the index of `value` in the pool. A name or literal used many
+    // times in a chunk only takes one slot.
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        let key = match &value {
+            Value::Str(string) => Some(ConstantKey::Str(Rc::clone(string))),
+            Value::Number(number) => Some(ConstantKey::Number(number.to_bits())),
+            _ => None,
+        };
+        if let Some(&index) = key.as_ref().and_then(|key| self.constant_index.get(key)) {
+            return index;
+        }
+
+        self.constants.push(value);
+        let index = self.constants.len() - 1;
+        if let Some(key) = key {
+            self.constant_index.insert(key, index);
+        }
+        index
+    }
+
+    pub fn line(&self, offset: usize) -> usize {
+        let mut end = 0;
+        for run in &self.lines {
+            end += run.count;
+            if offset < end {
+                return run.line;
+            }
+        }
+        self.lines.last().map_or(0, |run| run.line)
+    }
+}
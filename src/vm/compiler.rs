@@ -0,0 +1,1223 @@
+use crate::error::LoxError;
+use crate::token::{Object, Token};
+use crate::token_types::TokenType;
+use crate::vm::chunk::{Chunk, OpCode};
+use crate::vm::value::{Function, Value};
+use std::rc::Rc;
+
+const MAX_LOCALS: usize = 256;
+const MAX_UPVALUES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Precedence {
+    None,
+    Comma,       // ,
+    Assignment,  // =
+    Conditional, // ?:
+    Or,          // or
+    And,        // and
+    Equality,   // == !=
+    Comparison, // < > <= >=
+    Term,       // + -
+    Factor,     // * /
+    Unary,      // ! -
+    Call,       // . ()
+    Primary,
+}
+
+impl Precedence {
+    fn next(self) -> Precedence {
+        match self {
+            Precedence::None => Precedence::Comma,
+            Precedence::Comma => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Conditional,
+            Precedence::Conditional => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Call,
+            Precedence::Call | Precedence::Primary => Precedence::Primary,
+        }
+    }
+}
+
+type ParseFn<'a> = fn(&mut Compiler<'a>, bool) -> Result<(), LoxError>;
+
+struct ParseRule<'a> {
+    prefix: Option<ParseFn<'a>>,
+    infix: Option<ParseFn<'a>>,
+    precedence: Precedence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+impl FunctionKind {
+    // How the function is described in error messages.
+    fn label(self) -> &'static str {
+        match self {
+            FunctionKind::Method | FunctionKind::Initializer => "method",
+            FunctionKind::Script | FunctionKind::Function => "function",
+        }
+    }
+}
+
+struct Local {
+    name: String,
+    // `None` while the variable's initializer is being compiled.
+    depth: Option<usize>,
+    is_captured: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct UpvalueRef {
+    index: u8,
+    is_local: bool,
+}
+
+// Per-function compilation state; nested function declarations push a new one.
+struct FunctionState {
+    function: Function,
+    kind: FunctionKind,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueRef>,
+    scope_depth: usize,
+}
+
+impl FunctionState {
+    fn new(kind: FunctionKind, name: &str) -> Self {
+        FunctionState {
+            function: Function {
+                name: name.to_string(),
+                ..Function::default()
+            },
+            kind,
+            // Slot zero holds the function being called, or in a method the
+            // instance it was called on.
+            locals: vec![Local {
+                name: match kind {
+                    FunctionKind::Method | FunctionKind::Initializer => "this".to_string(),
+                    FunctionKind::Script | FunctionKind::Function => String::new(),
+                },
+                depth: Some(0),
+                is_captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+        }
+    }
+}
+
+// Per-class compilation state, for checking uses of `this` and `super`.
+struct ClassState {
+    has_superclass: bool,
+}
+
+// Compiles a whole program straight from the scanner's tokens to bytecode in
+// a single pass, returning the top-level script function, or every syntax
+// error in the program.
+pub fn compile(tokens: &[Token]) -> Result<Rc<Function>, Vec<LoxError>> {
+    let mut compiler = Compiler {
+        tokens,
+        current: 0,
+        states: vec![FunctionState::new(FunctionKind::Script, "")],
+        classes: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    while !compiler.check(TokenType::Eof) {
+        compiler.declaration();
+    }
+    if !compiler.errors.is_empty() {
+        return Err(compiler.errors);
+    }
+    let (function, _) = compiler.end_function();
+    Ok(Rc::new(function))
+}
+
+struct Compiler<'a> {
+    tokens: &'a [Token],
+    current: usize,
+    states: Vec<FunctionState>,
+    classes: Vec<ClassState>,
+    errors: Vec<LoxError>,
+}
+
+impl<'a> Compiler<'a> {
+    // DECLARATIONS AND STATEMENTS
+
+    // Compiles one declaration, recovering from a syntax error in it like the
+    // tree-walker's parser does: the error is recorded, whatever the failed
+    // declaration left half-done is rolled back, and tokens are skipped to
+    // the next statement so later errors are found too. A program with
+    // errors is never run, so the bytecode emitted along the way is moot.
+    fn declaration(&mut self) {
+        let (states, classes) = (self.states.len(), self.classes.len());
+        let (locals, scope_depth) = (self.state().locals.len(), self.state().scope_depth);
+        if let Err(err) = self.declaration_or_error() {
+            self.errors.push(err);
+            self.states.truncate(states);
+            self.classes.truncate(classes);
+            let state = self.state_mut();
+            state.locals.truncate(locals);
+            state.scope_depth = scope_depth;
+            self.synchronize();
+        }
+    }
+
+    // Discards tokens until we are probably at the start of the next
+    // statement: just past a ';' or at a keyword that begins one. Unlike the
+    // parser this doesn't skip a token first, since an expression error here
+    // is reported after its token was consumed, and every declaration
+    // consumes at least one token before it can fail.
+    fn synchronize(&mut self) {
+        while !self.check(TokenType::Eof) {
+            if self.previous().token_type == TokenType::Semicolon {
+                return;
+            }
+            match self.peek().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    fn declaration_or_error(&mut self) -> Result<(), LoxError> {
+        if self.is_match(TokenType::Class) {
+            self.class_declaration()
+        } else if self.is_match(TokenType::Fun) {
+            self.fun_declaration()
+        } else if self.is_match(TokenType::Var) {
+            self.var_declaration()
+        } else {
+            self.statement()
+        }
+    }
+
+    fn class_declaration(&mut self) -> Result<(), LoxError> {
+        let class_name = self
+            .consume(TokenType::Identifier, "Expect class name.")?
+            .clone();
+        let name_constant = self.identifier_constant(&class_name)?;
+        self.declare_variable(&class_name)?;
+
+        self.emit_constant_op(OpCode::Class, name_constant);
+        self.define_variable(name_constant);
+        self.classes.push(ClassState {
+            has_superclass: false,
+        });
+
+        if self.is_match(TokenType::Less) {
+            let superclass = self
+                .consume(TokenType::Identifier, "Expect superclass name.")?
+                .clone();
+            if superclass.lexeme == class_name.lexeme {
+                return Err(LoxError::parse_error(
+                    &superclass,
+                    "A class can't inherit from itself.",
+                ));
+            }
+            self.named_variable(&superclass, false)?;
+
+            // Methods find the superclass through a local named `super` in a
+            // scope wrapped around the class body.
+            self.begin_scope();
+            self.declare_variable(&Self::synthetic_token(&superclass, "super"))?;
+            self.define_variable(0);
+
+            self.named_variable(&class_name, false)?;
+            self.emit_op(OpCode::Inherit);
+            self.classes
+                .last_mut()
+                .expect("class state was just pushed")
+                .has_superclass = true;
+        }
+
+        // Keep the class on the stack while its methods are attached.
+        self.named_variable(&class_name, false)?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
+            self.method()?;
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+        self.emit_op(OpCode::Pop);
+
+        let class = self.classes.pop().expect("class state was just pushed");
+        if class.has_superclass {
+            self.end_scope();
+        }
+        Ok(())
+    }
+
+    fn method(&mut self) -> Result<(), LoxError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect method name.")?
+            .clone();
+        let constant = self.identifier_constant(&name)?;
+        let kind = if name.lexeme == "init" {
+            FunctionKind::Initializer
+        } else {
+            FunctionKind::Method
+        };
+        self.function(kind)?;
+        self.emit_constant_op(OpCode::Method, constant);
+        Ok(())
+    }
+
+    fn fun_declaration(&mut self) -> Result<(), LoxError> {
+        let global = self.parse_variable("Expect function name.")?;
+        // A function may refer to itself, so it is usable before its body ends.
+        self.mark_initialized();
+        self.function(FunctionKind::Function)?;
+        self.define_variable(global);
+        Ok(())
+    }
+
+    fn function(&mut self, kind: FunctionKind) -> Result<(), LoxError> {
+        let name = self.previous().lexeme.clone();
+        self.states.push(FunctionState::new(kind, &name));
+        self.begin_scope();
+
+        let label = kind.label();
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {label} name."),
+        )?;
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if self.state().function.arity == 255 {
+                    return Err(LoxError::parse_error(
+                        self.peek(),
+                        "Can't have more than 255 parameters.",
+                    ));
+                }
+                self.state_mut().function.arity += 1;
+                let constant = self.parse_variable("Expect parameter name.")?;
+                self.define_variable(constant);
+                if !self.is_match(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {label} body."),
+        )?;
+        self.block()?;
+
+        // No `end_scope`: the whole frame is discarded when the function returns.
+        let (function, upvalues) = self.end_function();
+        let constant = self.make_constant(Value::Function(Rc::new(function)))?;
+        self.emit_constant_op(OpCode::Closure, constant);
+        for upvalue in upvalues {
+            self.emit_bytes(upvalue.is_local as u8, upvalue.index);
+        }
+        Ok(())
+    }
+
+    fn var_declaration(&mut self) -> Result<(), LoxError> {
+        let global = self.parse_variable("Expect variable name.")?;
+
+        if self.is_match(TokenType::Equal) {
+            self.expression()?;
+        } else {
+            self.emit_op(OpCode::Nil);
+        }
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+
+        self.define_variable(global);
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), LoxError> {
+        if self.is_match(TokenType::Print) {
+            self.print_statement()
+        } else if self.is_match(TokenType::For) {
+            self.for_statement()
+        } else if self.is_match(TokenType::If) {
+            self.if_statement()
+        } else if self.is_match(TokenType::Return) {
+            self.return_statement()
+        } else if self.is_match(TokenType::While) {
+            self.while_statement()
+        } else if self.is_match(TokenType::LeftBrace) {
+            self.begin_scope();
+            self.block()?;
+            self.end_scope();
+            Ok(())
+        } else {
+            self.expression_statement()
+        }
+    }
+
+    fn print_statement(&mut self) -> Result<(), LoxError> {
+        self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+        self.emit_op(OpCode::Print);
+        Ok(())
+    }
+
+    fn for_statement(&mut self) -> Result<(), LoxError> {
+        self.begin_scope();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+        if self.is_match(TokenType::Semicolon) {
+            // No initializer.
+        } else if self.is_match(TokenType::Var) {
+            self.var_declaration()?;
+        } else {
+            self.expression_statement()?;
+        }
+
+        let mut loop_start = self.chunk().code.len();
+        let mut exit_jump = None;
+        if !self.is_match(TokenType::Semicolon) {
+            self.expression()?;
+            self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+            exit_jump = Some(self.emit_jump(OpCode::JumpIfFalse));
+            self.emit_op(OpCode::Pop);
+        }
+
+        // The increment is compiled before the body but runs after it, so
+        // jump over it on the way in and loop back to it at the end.
+        if !self.is_match(TokenType::RightParen) {
+            let body_jump = self.emit_jump(OpCode::Jump);
+            let increment_start = self.chunk().code.len();
+            self.expression()?;
+            self.emit_op(OpCode::Pop);
+            self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+            self.emit_loop(loop_start)?;
+            loop_start = increment_start;
+            self.patch_jump(body_jump)?;
+        }
+
+        self.statement()?;
+        self.emit_loop(loop_start)?;
+
+        if let Some(exit_jump) = exit_jump {
+            self.patch_jump(exit_jump)?;
+            self.emit_op(OpCode::Pop);
+        }
+
+        self.end_scope();
+        Ok(())
+    }
+
+    fn if_statement(&mut self) -> Result<(), LoxError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+        self.statement()?;
+
+        let else_jump = self.emit_jump(OpCode::Jump);
+        self.patch_jump(then_jump)?;
+        self.emit_op(OpCode::Pop);
+
+        if self.is_match(TokenType::Else) {
+            self.statement()?;
+        }
+        self.patch_jump(else_jump)
+    }
+
+    fn return_statement(&mut self) -> Result<(), LoxError> {
+        if self.state().kind == FunctionKind::Script {
+            return Err(LoxError::parse_error(
+                self.previous(),
+                "Can't return from top-level code.",
+            ));
+        }
+
+        if self.is_match(TokenType::Semicolon) {
+            self.emit_return();
+        } else {
+            if self.state().kind == FunctionKind::Initializer {
+                return Err(LoxError::parse_error(
+                    self.previous(),
+                    "Can't return a value from an initializer.",
+                ));
+            }
+            self.expression()?;
+            self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+            self.emit_op(OpCode::Return);
+        }
+        Ok(())
+    }
+
+    fn while_statement(&mut self) -> Result<(), LoxError> {
+        let loop_start = self.chunk().code.len();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+        self.statement()?;
+        self.emit_loop(loop_start)?;
+
+        self.patch_jump(exit_jump)?;
+        self.emit_op(OpCode::Pop);
+        Ok(())
+    }
+
+    fn expression_statement(&mut self) -> Result<(), LoxError> {
+        self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        self.emit_op(OpCode::Pop);
+        Ok(())
+    }
+
+    fn block(&mut self) -> Result<(), LoxError> {
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
+            self.declaration();
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(())
+    }
+
+    // EXPRESSIONS
+
+    fn expression(&mut self) -> Result<(), LoxError> {
+        self.parse_precedence(Precedence::Comma)
+    }
+
+    // An expression that stops before any top-level comma, for call
+    // arguments and the right-hand side of `=`.
+    fn assignment(&mut self) -> Result<(), LoxError> {
+        self.parse_precedence(Precedence::Assignment)
+    }
+
+    fn parse_precedence(&mut self, precedence: Precedence) -> Result<(), LoxError> {
+        // `advance` stops at the end of input, which would leave `previous`
+        // on the last prefix token and run its rule again forever.
+        if self.check(TokenType::Eof) {
+            return Err(LoxError::parse_error(self.peek(), "Expect expression."));
+        }
+        self.advance();
+        let Some(prefix) = Self::rule(self.previous().token_type.clone()).prefix else {
+            return Err(LoxError::parse_error(self.previous(), "Expect expression."));
+        };
+
+        // Only the lowest-precedence operand may be the target of an `=`.
+        let can_assign = precedence <= Precedence::Assignment;
+        prefix(self, can_assign)?;
+
+        while precedence <= Self::rule(self.peek().token_type.clone()).precedence {
+            self.advance();
+            if let Some(infix) = Self::rule(self.previous().token_type.clone()).infix {
+                infix(self, can_assign)?;
+            }
+        }
+
+        if can_assign && self.is_match(TokenType::Equal) {
+            return Err(LoxError::parse_error(
+                self.previous(),
+                "Invalid assignment target.",
+            ));
+        }
+        Ok(())
+    }
+
+    fn rule(token_type: TokenType) -> ParseRule<'a> {
+        let (prefix, infix, precedence): (Option<ParseFn<'a>>, Option<ParseFn<'a>>, Precedence) =
+            match token_type {
+                TokenType::LeftParen => (Some(Self::grouping), Some(Self::call), Precedence::Call),
+                TokenType::Dot => (None, Some(Self::dot), Precedence::Call),
+                TokenType::Minus => (Some(Self::unary), Some(Self::binary), Precedence::Term),
+                TokenType::Plus => (
+                    Some(Self::missing_left_operand),
+                    Some(Self::binary),
+                    Precedence::Term,
+                ),
+                TokenType::Slash | TokenType::Star => (
+                    Some(Self::missing_left_operand),
+                    Some(Self::binary),
+                    Precedence::Factor,
+                ),
+                TokenType::Bang => (Some(Self::unary), None, Precedence::None),
+                TokenType::BangEqual | TokenType::EqualEqual => (
+                    Some(Self::missing_left_operand),
+                    Some(Self::binary),
+                    Precedence::Equality,
+                ),
+                TokenType::Greater
+                | TokenType::GreaterEqual
+                | TokenType::Less
+                | TokenType::LessEqual => (
+                    Some(Self::missing_left_operand),
+                    Some(Self::binary),
+                    Precedence::Comparison,
+                ),
+                TokenType::Identifier => (Some(Self::variable), None, Precedence::None),
+                TokenType::String | TokenType::Number => {
+                    (Some(Self::literal), None, Precedence::None)
+                }
+                TokenType::Interpolation => (Some(Self::interpolation), None, Precedence::None),
+                TokenType::False | TokenType::True | TokenType::Nil => {
+                    (Some(Self::literal), None, Precedence::None)
+                }
+                TokenType::Comma => (None, Some(Self::comma), Precedence::Comma),
+                TokenType::Question => {
+                    (None, Some(Self::conditional), Precedence::Conditional)
+                }
+                TokenType::And => (
+                    Some(Self::missing_left_operand),
+                    Some(Self::and),
+                    Precedence::And,
+                ),
+                TokenType::Or => (
+                    Some(Self::missing_left_operand),
+                    Some(Self::or),
+                    Precedence::Or,
+                ),
+                TokenType::This => (Some(Self::this), None, Precedence::None),
+                TokenType::Super => (Some(Self::super_), None, Precedence::None),
+                _ => (None, None, Precedence::None),
+            };
+        ParseRule {
+            prefix,
+            infix,
+            precedence,
+        }
+    }
+
+    fn grouping(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
+        Ok(())
+    }
+
+    fn call(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        let mut arg_count: usize = 0;
+        if !self.check(TokenType::RightParen) {
+            loop {
+                self.assignment()?;
+                if arg_count == 255 {
+                    return Err(LoxError::parse_error(
+                        self.previous(),
+                        "Can't have more than 255 arguments.",
+                    ));
+                }
+                arg_count += 1;
+                if !self.is_match(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+        self.emit_bytes(OpCode::Call as u8, arg_count as u8);
+        Ok(())
+    }
+
+    fn dot(&mut self, can_assign: bool) -> Result<(), LoxError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect property name after '.'.")?
+            .clone();
+        let constant = self.identifier_constant(&name)?;
+
+        if can_assign && self.is_match(TokenType::Equal) {
+            self.assignment()?;
+            self.emit_constant_op(OpCode::SetProperty, constant);
+        } else {
+            self.emit_constant_op(OpCode::GetProperty, constant);
+        }
+        Ok(())
+    }
+
+    fn this(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        if self.classes.is_empty() {
+            return Err(LoxError::parse_error(
+                self.previous(),
+                "Can't use 'this' outside of a class.",
+            ));
+        }
+        // `this` is the local in slot zero of every method.
+        self.variable(false)
+    }
+
+    fn super_(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        let keyword = self.previous().clone();
+        match self.classes.last() {
+            None => {
+                return Err(LoxError::parse_error(
+                    &keyword,
+                    "Can't use 'super' outside of a class.",
+                ))
+            }
+            Some(class) if !class.has_superclass => {
+                return Err(LoxError::parse_error(
+                    &keyword,
+                    "Can't use 'super' in a class with no superclass.",
+                ))
+            }
+            Some(_) => {}
+        }
+
+        self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+        let method = self
+            .consume(TokenType::Identifier, "Expect superclass method name.")?
+            .clone();
+        let constant = self.identifier_constant(&method)?;
+
+        self.named_variable(&Self::synthetic_token(&keyword, "this"), false)?;
+        self.named_variable(&Self::synthetic_token(&keyword, "super"), false)?;
+        self.emit_constant_op(OpCode::GetSuper, constant);
+        Ok(())
+    }
+
+    fn unary(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        let operator = self.previous().token_type.clone();
+        self.parse_precedence(Precedence::Unary)?;
+
+        match operator {
+            TokenType::Bang => self.emit_op(OpCode::Not),
+            TokenType::Minus => self.emit_op(OpCode::Negate),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // A binary operator with nothing on its left, like `+ 3`. The right
+    // operand is still compiled so the error points at the operator.
+    fn missing_left_operand(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        let operator = self.previous().clone();
+        let precedence = Self::rule(operator.token_type.clone()).precedence;
+        self.parse_precedence(precedence.next())?;
+        Err(LoxError::parse_error(
+            &operator,
+            &format!("Missing left-hand operand for '{}'.", operator.lexeme),
+        ))
+    }
+
+    fn binary(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        let operator = self.previous().token_type.clone();
+        let precedence = Self::rule(operator.clone()).precedence;
+        // Binary operators are left-associative, so the right operand binds
+        // one level tighter.
+        self.parse_precedence(precedence.next())?;
+
+        match operator {
+            TokenType::BangEqual => self.emit_ops(OpCode::Equal, OpCode::Not),
+            TokenType::EqualEqual => self.emit_op(OpCode::Equal),
+            TokenType::Greater => self.emit_op(OpCode::Greater),
+            TokenType::GreaterEqual => self.emit_ops(OpCode::Less, OpCode::Not),
+            TokenType::Less => self.emit_op(OpCode::Less),
+            TokenType::LessEqual => self.emit_ops(OpCode::Greater, OpCode::Not),
+            TokenType::Plus => self.emit_op(OpCode::Add),
+            TokenType::Minus => self.emit_op(OpCode::Subtract),
+            TokenType::Star => self.emit_op(OpCode::Multiply),
+            TokenType::Slash => self.emit_op(OpCode::Divide),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn comma(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        // Only the right operand's value is kept.
+        self.emit_op(OpCode::Pop);
+        self.parse_precedence(Precedence::Comma.next())
+    }
+
+    fn conditional(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+        self.expression()?;
+        let end_jump = self.emit_jump(OpCode::Jump);
+
+        self.patch_jump(else_jump)?;
+        self.emit_op(OpCode::Pop);
+        self.consume(
+            TokenType::Colon,
+            "Expect ':' after then branch of conditional expression.",
+        )?;
+        // Right-associative: the else branch may itself be a conditional.
+        self.parse_precedence(Precedence::Conditional)?;
+        self.patch_jump(end_jump)
+    }
+
+    fn and(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+        self.parse_precedence(Precedence::And)?;
+        self.patch_jump(end_jump)
+    }
+
+    fn or(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+        let end_jump = self.emit_jump(OpCode::Jump);
+
+        self.patch_jump(else_jump)?;
+        self.emit_op(OpCode::Pop);
+
+        self.parse_precedence(Precedence::Or)?;
+        self.patch_jump(end_jump)
+    }
+
+    fn literal(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        match self.previous().token_type {
+            TokenType::False => self.emit_op(OpCode::False),
+            TokenType::True => self.emit_op(OpCode::True),
+            TokenType::Nil => self.emit_op(OpCode::Nil),
+            _ => {
+                let value = match &self.previous().literal {
+                    Some(Object::Num(value)) => Value::Number(*value),
+                    Some(Object::Str(value)) => Value::Str(Rc::from(value.as_str())),
+                    _ => Value::Nil,
+                };
+                self.emit_constant(value)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Pushes each string segment and expression of `"a ${b} c"` in order,
+    // then joins them all with a single `Interpolate`.
+    fn interpolation(&mut self, _can_assign: bool) -> Result<(), LoxError> {
+        let mut part_count: usize = 0;
+        loop {
+            self.literal(false)?;
+            if self.check(TokenType::InterpolationEnd) {
+                return Err(LoxError::parse_error(
+                    self.peek(),
+                    "Expect expression in string interpolation.",
+                ));
+            }
+            self.expression()?;
+            part_count += 2;
+            if !self.is_match(TokenType::Interpolation) {
+                break;
+            }
+        }
+        self.consume(
+            TokenType::InterpolationEnd,
+            "Expect '}' after interpolated expression.",
+        )?;
+        self.literal(false)?;
+        part_count += 1;
+        if part_count > u8::MAX as usize {
+            return Err(LoxError::parse_error(
+                self.previous(),
+                "Too many interpolated expressions in one string.",
+            ));
+        }
+        self.emit_bytes(OpCode::Interpolate as u8, part_count as u8);
+        Ok(())
+    }
+
+    fn variable(&mut self, can_assign: bool) -> Result<(), LoxError> {
+        let name = self.previous().clone();
+        self.named_variable(&name, can_assign)
+    }
+
+    fn named_variable(&mut self, name: &Token, can_assign: bool) -> Result<(), LoxError> {
+        let top = self.states.len() - 1;
+        let (get_op, set_op, arg) = if let Some(slot) = self.resolve_local(top, name)? {
+            (OpCode::GetLocal, OpCode::SetLocal, slot as u16)
+        } else if let Some(index) = self.resolve_upvalue(top, name)? {
+            (OpCode::GetUpvalue, OpCode::SetUpvalue, index as u16)
+        } else {
+            let constant = self.identifier_constant(name)?;
+            (OpCode::GetGlobal, OpCode::SetGlobal, constant)
+        };
+
+        let op = if can_assign && self.is_match(TokenType::Equal) {
+            self.assignment()?;
+            set_op
+        } else {
+            get_op
+        };
+        match op {
+            OpCode::GetGlobal | OpCode::SetGlobal => self.emit_constant_op(op, arg),
+            _ => self.emit_bytes(op as u8, arg as u8),
+        }
+        Ok(())
+    }
+
+    // VARIABLES
+
+    fn parse_variable(&mut self, message: &str) -> Result<u16, LoxError> {
+        let name = self.consume(TokenType::Identifier, message)?.clone();
+
+        self.declare_variable(&name)?;
+        if self.state().scope_depth > 0 {
+            return Ok(0);
+        }
+        self.identifier_constant(&name)
+    }
+
+    fn identifier_constant(&mut self, name: &Token) -> Result<u16, LoxError> {
+        self.make_constant(Value::Str(Rc::from(name.lexeme.as_str())))
+    }
+
+    fn declare_variable(&mut self, name: &Token) -> Result<(), LoxError> {
+        let state = self.state();
+        if state.scope_depth == 0 {
+            return Ok(());
+        }
+
+        let duplicate = state
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth.is_none_or(|depth| depth >= state.scope_depth))
+            .any(|local| local.name == name.lexeme);
+        if duplicate {
+            return Err(LoxError::parse_error(
+                name,
+                "Already a variable with this name in this scope.",
+            ));
+        }
+
+        if state.locals.len() == MAX_LOCALS {
+            return Err(LoxError::parse_error(
+                name,
+                "Too many local variables in function.",
+            ));
+        }
+        self.state_mut().locals.push(Local {
+            name: name.lexeme.clone(),
+            depth: None,
+            is_captured: false,
+        });
+        Ok(())
+    }
+
+    fn define_variable(&mut self, global: u16) {
+        if self.state().scope_depth > 0 {
+            self.mark_initialized();
+            return;
+        }
+        self.emit_constant_op(OpCode::DefineGlobal, global);
+    }
+
+    fn mark_initialized(&mut self) {
+        let state = self.state_mut();
+        if state.scope_depth == 0 {
+            return;
+        }
+        let depth = state.scope_depth;
+        if let Some(local) = state.locals.last_mut() {
+            local.depth = Some(depth);
+        }
+    }
+
+    fn resolve_local(&self, state: usize, name: &Token) -> Result<Option<u8>, LoxError> {
+        for (slot, local) in self.states[state].locals.iter().enumerate().rev() {
+            if local.name == name.lexeme {
+                if local.depth.is_none() {
+                    return Err(LoxError::parse_error(
+                        name,
+                        "Can't read local variable in its own initializer.",
+                    ));
+                }
+                return Ok(Some(slot as u8));
+            }
+        }
+        Ok(None)
+    }
+
+    // Finds `name` in an enclosing function, threading an upvalue through
+    // every function in between so each one captures it from its parent.
+    fn resolve_upvalue(&mut self, state: usize, name: &Token) -> Result<Option<u8>, LoxError> {
+        if state == 0 {
+            return Ok(None);
+        }
+
+        if let Some(local) = self.resolve_local(state - 1, name)? {
+            self.states[state - 1].locals[local as usize].is_captured = true;
+            return self.add_upvalue(state, name, local, true).map(Some);
+        }
+
+        if let Some(upvalue) = self.resolve_upvalue(state - 1, name)? {
+            return self.add_upvalue(state, name, upvalue, false).map(Some);
+        }
+
+        Ok(None)
+    }
+
+    fn add_upvalue(
+        &mut self,
+        state: usize,
+        name: &Token,
+        index: u8,
+        is_local: bool,
+    ) -> Result<u8, LoxError> {
+        let upvalue = UpvalueRef { index, is_local };
+        let upvalues = &mut self.states[state].upvalues;
+        if let Some(existing) = upvalues.iter().position(|candidate| *candidate == upvalue) {
+            return Ok(existing as u8);
+        }
+
+        if upvalues.len() == MAX_UPVALUES {
+            return Err(LoxError::parse_error(
+                name,
+                "Too many closure variables in function.",
+            ));
+        }
+        upvalues.push(upvalue);
+        let count = upvalues.len();
+        self.states[state].function.upvalue_count = count;
+        Ok((count - 1) as u8)
+    }
+
+    fn begin_scope(&mut self) {
+        self.state_mut().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.state_mut().scope_depth -= 1;
+
+        loop {
+            let state = self.state();
+            let Some(local) = state.locals.last() else {
+                break;
+            };
+            if local.depth.is_none_or(|depth| depth <= state.scope_depth) {
+                break;
+            }
+
+            let op = if local.is_captured {
+                OpCode::CloseUpvalue
+            } else {
+                OpCode::Pop
+            };
+            self.emit_op(op);
+            self.state_mut().locals.pop();
+        }
+    }
+
+    fn end_function(&mut self) -> (Function, Vec<UpvalueRef>) {
+        self.emit_return();
+        let state = self
+            .states
+            .pop()
+            .expect("function state stack is never empty");
+        (state.function, state.upvalues)
+    }
+
+    // EMITTING BYTECODE
+
+    fn state(&self) -> &FunctionState {
+        self.states
+            .last()
+            .expect("function state stack is never empty")
+    }
+
+    fn state_mut(&mut self) -> &mut FunctionState {
+        self.states
+            .last_mut()
+            .expect("function state stack is never empty")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state_mut().function.chunk
+    }
+
+    fn emit_byte(&mut self, byte: u8) {
+        let line = self.previous().span.line;
+        self.chunk().write(byte, line);
+    }
+
+    fn emit_op(&mut self, op: OpCode) {
+        self.emit_byte(op as u8);
+    }
+
+    fn emit_ops(&mut self, first: OpCode, second: OpCode) {
+        self.emit_op(first);
+        self.emit_op(second);
+    }
+
+    fn emit_bytes(&mut self, first: u8, second: u8) {
+        self.emit_byte(first);
+        self.emit_byte(second);
+    }
+
+    // An initializer always returns the instance, even from a bare `return;`.
+    fn emit_return(&mut self) {
+        if self.state().kind == FunctionKind::Initializer {
+            self.emit_bytes(OpCode::GetLocal as u8, 0);
+            self.emit_op(OpCode::Return);
+        } else {
+            self.emit_ops(OpCode::Nil, OpCode::Return);
+        }
+    }
+
+    fn make_constant(&mut self, value: Value) -> Result<u16, LoxError> {
+        let constant = self.chunk().add_constant(value);
+        u16::try_from(constant)
+            .map_err(|_| LoxError::parse_error(self.previous(), "Too many constants in one chunk."))
+    }
+
+    fn emit_constant(&mut self, value: Value) -> Result<(), LoxError> {
+        let constant = self.make_constant(value)?;
+        self.emit_constant_op(OpCode::Constant, constant);
+        Ok(())
+    }
+
+    fn emit_constant_op(&mut self, op: OpCode, constant: u16) {
+        let [high, low] = constant.to_be_bytes();
+        self.emit_op(op);
+        self.emit_bytes(high, low);
+    }
+
+    // Emits a jump with a placeholder operand and returns the operand's offset
+    // so it can be patched once the target is known.
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit_op(op);
+        self.emit_bytes(0xff, 0xff);
+        self.chunk().code.len() - 2
+    }
+
+    fn patch_jump(&mut self, offset: usize) -> Result<(), LoxError> {
+        // -2 to adjust for the bytecode for the jump offset itself.
+        let jump = self.chunk().code.len() - offset - 2;
+        let jump = u16::try_from(jump)
+            .map_err(|_| LoxError::parse_error(self.previous(), "Too much code to jump over."))?;
+
+        let [high, low] = jump.to_be_bytes();
+        let code = &mut self.chunk().code;
+        code[offset] = high;
+        code[offset + 1] = low;
+        Ok(())
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) -> Result<(), LoxError> {
+        self.emit_op(OpCode::Loop);
+
+        let offset = self.chunk().code.len() - loop_start + 2;
+        let offset = u16::try_from(offset)
+            .map_err(|_| LoxError::parse_error(self.previous(), "Loop body too large."))?;
+        let [high, low] = offset.to_be_bytes();
+        self.emit_bytes(high, low);
+        Ok(())
+    }
+
+    // An identifier the compiler refers to that isn't in the source, like
+    // the hidden `super` local. It borrows `near`'s position for errors.
+    fn synthetic_token(near: &Token, name: &str) -> Token {
+        Token::new(TokenType::Identifier, name.to_string(), None, near.span)
+    }
+
+    // TOKEN STREAM
+
+    fn advance(&mut self) {
+        if !self.check(TokenType::Eof) {
+            self.current += 1;
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current.saturating_sub(1)]
+    }
+
+    fn check(&self, token_type: TokenType) -> bool {
+        self.peek().token_type == token_type
+    }
+
+    fn is_match(&mut self, token_type: TokenType) -> bool {
+        if !self.check(token_type) {
+            return false;
+        }
+        self.advance();
+        true
+    }
+
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<&Token, LoxError> {
+        if self.check(token_type) {
+            self.advance();
+            return Ok(self.previous());
+        }
+        Err(LoxError::parse_error(self.peek(), message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn compile_errors(source: &str) -> Vec<LoxError> {
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = scanner.scan_tokens().expect("source should scan");
+        let Err(errors) = compile(tokens) else {
+            panic!("{source} should not compile");
+        };
+        errors
+    }
+
+    fn compile_error(source: &str) -> LoxError {
+        let mut errors = compile_errors(source);
+        assert_eq!(errors.len(), 1, "{source}: {errors:?}");
+        errors.remove(0)
+    }
+
+    #[test]
+    fn expression_cut_off_by_end_of_input() {
+        for source in ["(", "print -", "print !", "var x = ("] {
+            let error = compile_error(source);
+            assert_eq!(error.message(), "Expect expression.", "{source}");
+            assert_eq!(
+                error.to_string(),
+                "[line 1] Parse error at end: Expect expression."
+            );
+        }
+    }
+
+    #[test]
+    fn trailing_operator_is_missing_its_operand() {
+        for source in ["print 1 +", "print +", "print 1 * 2 -", "print 1 +;"] {
+            let error = compile_error(source);
+            assert_eq!(error.message(), "Expect expression.", "{source}");
+        }
+    }
+
+    #[test]
+    fn every_syntax_error_is_reported() {
+        let source = "fun f() {\n  var = 1;\n  print 2;\n}\n\
+                      class A {\n  m() { print ; }\n}\n\
+                      { var x = ; print x; }\n\
+                      print 4";
+        let located: Vec<(String, usize)> = compile_errors(source)
+            .iter()
+            .map(|err| (err.message().to_string(), err.details().line))
+            .collect();
+        let expected = [
+            ("Expect variable name.", 2),
+            ("Expect expression.", 6),
+            ("Expect expression.", 8),
+            ("Expect ';' after value.", 9),
+        ];
+        let expected: Vec<(String, usize)> = expected
+            .iter()
+            .map(|&(message, line)| (message.to_string(), line))
+            .collect();
+        assert_eq!(located, expected);
+    }
+}
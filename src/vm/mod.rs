@@ -0,0 +1,537 @@
+pub mod chunk;
+pub mod compiler;
+pub mod value;
+
+use crate::error::LoxError;
+use crate::interpreter::MAX_CALL_DEPTH;
+use crate::token::Token;
+use chunk::OpCode;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use value::{BoundMethod, Class, Closure, Instance, Native, Upvalue, Value};
+
+// The same limit as the tree-walker, so both backends accept the same scripts.
+const FRAMES_MAX: usize = MAX_CALL_DEPTH;
+
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    // Index of the frame's slot zero on the value stack.
+    slot_base: usize,
+}
+
+// A stack-based virtual machine executing the bytecode produced by
+// `compiler::compile`. Globals persist across calls to `interpret`, so a
+// single VM can back a whole REPL session.
+pub struct VM {
+    frames: Vec<CallFrame>,
+    stack: Vec<Value>,
+    globals: HashMap<Rc<str>, Value>,
+    // Upvalues still pointing into the stack, ordered by stack slot.
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VM {
+    pub fn new() -> Self {
+        let mut vm = VM {
+            frames: Vec::with_capacity(FRAMES_MAX),
+            stack: Vec::new(),
+            globals: HashMap::new(),
+            open_upvalues: Vec::new(),
+        };
+        vm.register_native("clock", 0, clock);
+        vm
+    }
+
+    // Compiles and runs a program. Every syntax error is returned; at run
+    // time the first error stops the program and is returned on its own.
+    pub fn interpret(&mut self, tokens: &[Token]) -> Result<(), Vec<LoxError>> {
+        let function = compiler::compile(tokens)?;
+        let closure = Rc::new(Closure {
+            function,
+            upvalues: Vec::new(),
+        });
+
+        self.stack.push(Value::Closure(Rc::clone(&closure)));
+        let result = self.call(closure, 0).and_then(|_| self.run());
+        if result.is_err() {
+            self.reset_stack();
+        }
+        result.map_err(|err| vec![err])
+    }
+
+    // Exposes a Rust function to Lox code as a global, like
+    // `Interpreter::register_native`, so embedders can give scripts the same
+    // host functions on either backend.
+    pub fn register_native<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, LoxError> + 'static,
+    {
+        let native = Native {
+            name: name.to_string(),
+            arity,
+            function: Rc::new(function),
+        };
+        self.globals
+            .insert(Rc::from(name), Value::Native(Rc::new(native)));
+    }
+
+    fn run(&mut self) -> Result<(), LoxError> {
+        loop {
+            let byte = self.read_byte();
+            let op = OpCode::try_from(byte)
+                .map_err(|byte| self.runtime_error(&format!("Unknown opcode {byte}.")))?;
+
+            match op {
+                OpCode::Constant => {
+                    let constant = self.read_constant();
+                    self.push(constant);
+                }
+                OpCode::Nil => self.push(Value::Nil),
+                OpCode::True => self.push(Value::Bool(true)),
+                OpCode::False => self.push(Value::Bool(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal => {
+                    let slot = self.read_byte() as usize;
+                    let value = self.stack[self.frame().slot_base + slot].clone();
+                    self.push(value);
+                }
+                OpCode::SetLocal => {
+                    let slot = self.read_byte() as usize;
+                    let base = self.frame().slot_base;
+                    self.stack[base + slot] = self.peek(0).clone();
+                }
+                OpCode::GetGlobal => {
+                    let name = self.read_string();
+                    match self.globals.get(&name) {
+                        Some(value) => {
+                            let value = value.clone();
+                            self.push(value);
+                        }
+                        None => {
+                            return Err(
+                                self.runtime_error(&format!("Undefined variable '{}'.", name))
+                            )
+                        }
+                    }
+                }
+                OpCode::DefineGlobal => {
+                    let name = self.read_string();
+                    let value = self.pop();
+                    self.globals.insert(name, value);
+                }
+                OpCode::SetGlobal => {
+                    let name = self.read_string();
+                    let value = self.peek(0).clone();
+                    match self.globals.get_mut(&name) {
+                        Some(slot) => *slot = value,
+                        None => {
+                            return Err(
+                                self.runtime_error(&format!("Undefined variable '{}'.", name))
+                            )
+                        }
+                    }
+                }
+                OpCode::GetUpvalue => {
+                    let index = self.read_byte() as usize;
+                    let upvalue = Rc::clone(&self.frame().closure.upvalues[index]);
+                    let value = match &*upvalue.borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.push(value);
+                }
+                OpCode::SetUpvalue => {
+                    let index = self.read_byte() as usize;
+                    let upvalue = Rc::clone(&self.frame().closure.upvalues[index]);
+                    let value = self.peek(0).clone();
+                    let mut upvalue = upvalue.borrow_mut();
+                    match &mut *upvalue {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                }
+                OpCode::Equal => {
+                    let b = self.pop();
+                    let a = self.pop();
+                    self.push(Value::Bool(a == b));
+                }
+                OpCode::Greater => {
+                    let (a, b) = self.pop_numbers()?;
+                    self.push(Value::Bool(a > b));
+                }
+                OpCode::Less => {
+                    let (a, b) = self.pop_numbers()?;
+                    self.push(Value::Bool(a < b));
+                }
+                OpCode::Add => match (self.peek(1), self.peek(0)) {
+                    (Value::Number(_), Value::Number(_)) => {
+                        let (a, b) = self.pop_numbers()?;
+                        self.push(Value::Number(a + b));
+                    }
+                    (Value::Str(a), Value::Str(b)) => {
+                        let joined: Rc<str> = Rc::from(format!("{a}{b}"));
+                        self.pop();
+                        self.pop();
+                        self.push(Value::Str(joined));
+                    }
+                    _ => {
+                        return Err(
+                            self.runtime_error("Operands must be two numbers or two strings.")
+                        )
+                    }
+                },
+                OpCode::Subtract => {
+                    let (a, b) = self.pop_numbers()?;
+                    self.push(Value::Number(a - b));
+                }
+                OpCode::Multiply => {
+                    let (a, b) = self.pop_numbers()?;
+                    self.push(Value::Number(a * b));
+                }
+                OpCode::Divide => {
+                    let (a, b) = self.pop_numbers()?;
+                    self.push(Value::Number(a / b));
+                }
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.push(Value::Bool(value.is_falsey()));
+                }
+                OpCode::Interpolate => {
+                    let count = self.read_byte() as usize;
+                    let parts = self.stack.split_off(self.stack.len() - count);
+                    let text: String = parts.iter().map(Value::to_string).collect();
+                    self.push(Value::Str(Rc::from(text)));
+                }
+                OpCode::Negate => match self.pop() {
+                    Value::Number(value) => self.push(Value::Number(-value)),
+                    _ => return Err(self.runtime_error("Operand must be a number.")),
+                },
+                OpCode::Print => {
+                    let value = self.pop();
+                    println!("{}", value);
+                }
+                OpCode::Jump => {
+                    let offset = self.read_short();
+                    self.frame_mut().ip += offset;
+                }
+                OpCode::JumpIfFalse => {
+                    let offset = self.read_short();
+                    if self.peek(0).is_falsey() {
+                        self.frame_mut().ip += offset;
+                    }
+                }
+                OpCode::Loop => {
+                    let offset = self.read_short();
+                    self.frame_mut().ip -= offset;
+                }
+                OpCode::Call => {
+                    let arg_count = self.read_byte() as usize;
+                    self.call_value(self.peek(arg_count).clone(), arg_count)?;
+                }
+                OpCode::Closure => {
+                    let Value::Function(function) = self.read_constant() else {
+                        return Err(self.runtime_error("Closure operand must be a function."));
+                    };
+
+                    let mut upvalues = Vec::with_capacity(function.upvalue_count);
+                    for _ in 0..function.upvalue_count {
+                        let is_local = self.read_byte() == 1;
+                        let index = self.read_byte() as usize;
+                        if is_local {
+                            let slot = self.frame().slot_base + index;
+                            upvalues.push(self.capture_upvalue(slot));
+                        } else {
+                            upvalues.push(Rc::clone(&self.frame().closure.upvalues[index]));
+                        }
+                    }
+
+                    self.push(Value::Closure(Rc::new(Closure { function, upvalues })));
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+                OpCode::Class => {
+                    let name = self.read_string();
+                    self.push(Value::Class(Rc::new(Class::new(&name))));
+                }
+                OpCode::GetProperty => {
+                    let name = self.read_string();
+                    let Value::Instance(instance) = self.peek(0).clone() else {
+                        return Err(self.runtime_error("Only instances have properties."));
+                    };
+                    let field = instance.fields.borrow().get(&name).cloned();
+                    match field {
+                        Some(value) => {
+                            self.pop();
+                            self.push(value);
+                        }
+                        None => self.bind_method(&instance.class, &name)?,
+                    }
+                }
+                OpCode::SetProperty => {
+                    let name = self.read_string();
+                    let Value::Instance(instance) = self.peek(1).clone() else {
+                        return Err(self.runtime_error("Only instances have fields."));
+                    };
+                    let value = self.pop();
+                    instance.fields.borrow_mut().insert(name, value.clone());
+                    // Replace the instance with the assigned value.
+                    self.pop();
+                    self.push(value);
+                }
+                OpCode::Method => {
+                    let name = self.read_string();
+                    if let (Value::Class(class), Value::Closure(method)) =
+                        (self.peek(1), self.peek(0))
+                    {
+                        class.methods.borrow_mut().insert(name, Rc::clone(method));
+                    }
+                    self.pop();
+                }
+                OpCode::Inherit => {
+                    let Value::Class(superclass) = self.peek(1) else {
+                        return Err(self.runtime_error("Superclass must be a class."));
+                    };
+                    if let Value::Class(subclass) = self.peek(0) {
+                        let inherited = superclass.methods.borrow().clone();
+                        subclass.methods.borrow_mut().extend(inherited);
+                    }
+                    // Pop the subclass; the superclass stays as the `super` local.
+                    self.pop();
+                }
+                OpCode::GetSuper => {
+                    let name = self.read_string();
+                    let Value::Class(superclass) = self.pop() else {
+                        return Err(self.runtime_error("Superclass must be a class."));
+                    };
+                    self.bind_method(&superclass, &name)?;
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().expect("return outside of a call frame");
+                    self.close_upvalues(frame.slot_base);
+
+                    if self.frames.is_empty() {
+                        // Pop the script closure itself.
+                        self.stack.truncate(frame.slot_base);
+                        return Ok(());
+                    }
+
+                    self.stack.truncate(frame.slot_base);
+                    self.push(result);
+                }
+            }
+        }
+    }
+
+    fn call_value(&mut self, callee: Value, arg_count: usize) -> Result<(), LoxError> {
+        match callee {
+            Value::Closure(closure) => self.call(closure, arg_count),
+            Value::BoundMethod(bound) => {
+                // The receiver takes the callee's slot, where the method
+                // expects to find `this`.
+                let slot = self.stack.len() - arg_count - 1;
+                self.stack[slot] = bound.receiver.clone();
+                self.call(Rc::clone(&bound.method), arg_count)
+            }
+            Value::Class(class) => {
+                let slot = self.stack.len() - arg_count - 1;
+                self.stack[slot] = Value::Instance(Rc::new(Instance {
+                    class: Rc::clone(&class),
+                    fields: RefCell::new(HashMap::new()),
+                }));
+                let initializer = class.methods.borrow().get("init").cloned();
+                match initializer {
+                    Some(initializer) => self.call(initializer, arg_count),
+                    None if arg_count != 0 => Err(self.runtime_error(&format!(
+                        "Expected 0 arguments but got {}.",
+                        arg_count
+                    ))),
+                    None => Ok(()),
+                }
+            }
+            Value::Native(native) => {
+                if arg_count != native.arity {
+                    return Err(self.runtime_error(&format!(
+                        "Expected {} arguments but got {}.",
+                        native.arity, arg_count
+                    )));
+                }
+
+                let args_start = self.stack.len() - arg_count;
+                // Errors the native raised without a line are blamed on the
+                // call.
+                let result = (native.function)(&self.stack[args_start..]).map_err(|mut err| {
+                    if err.details().line == 0 {
+                        err.details_mut().line = self.current_line();
+                    }
+                    err
+                })?;
+                // Drop the arguments and the native itself.
+                self.stack.truncate(args_start - 1);
+                self.push(result);
+                Ok(())
+            }
+            _ => Err(self.runtime_error("Can only call functions and classes.")),
+        }
+    }
+
+    fn call(&mut self, closure: Rc<Closure>, arg_count: usize) -> Result<(), LoxError> {
+        if arg_count != closure.function.arity {
+            return Err(self.runtime_error(&format!(
+                "Expected {} arguments but got {}.",
+                closure.function.arity, arg_count
+            )));
+        }
+
+        if self.frames.len() == FRAMES_MAX {
+            return Err(self.runtime_error("Stack overflow."));
+        }
+
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            slot_base: self.stack.len() - arg_count - 1,
+        });
+        Ok(())
+    }
+
+    // Replaces the instance on top of the stack with its class's method
+    // `name`, bound to that instance.
+    fn bind_method(&mut self, class: &Class, name: &str) -> Result<(), LoxError> {
+        let Some(method) = class.methods.borrow().get(name).cloned() else {
+            return Err(self.runtime_error(&format!("Undefined property '{}'.", name)));
+        };
+        let receiver = self.pop();
+        self.push(Value::BoundMethod(Rc::new(BoundMethod { receiver, method })));
+        Ok(())
+    }
+
+    // Reuses an existing open upvalue for `slot` so that every closure
+    // capturing the same variable shares it.
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let existing = self
+            .open_upvalues
+            .iter()
+            .find(|upvalue| matches!(&*upvalue.borrow(), Upvalue::Open(open) if *open == slot));
+        if let Some(upvalue) = existing {
+            return Rc::clone(upvalue);
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        let position = self
+            .open_upvalues
+            .iter()
+            .position(|upvalue| matches!(&*upvalue.borrow(), Upvalue::Open(open) if *open > slot))
+            .unwrap_or(self.open_upvalues.len());
+        self.open_upvalues.insert(position, Rc::clone(&upvalue));
+        upvalue
+    }
+
+    // Moves every captured variable at or above `last` off the stack and
+    // into its upvalue, since those stack slots are about to be discarded.
+    fn close_upvalues(&mut self, last: usize) {
+        let stack = &self.stack;
+        self.open_upvalues.retain(|upvalue| {
+            let slot = match &*upvalue.borrow() {
+                Upvalue::Open(slot) if *slot >= last => *slot,
+                _ => return true,
+            };
+            upvalue.replace(Upvalue::Closed(stack[slot].clone()));
+            false
+        });
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().expect("no active call frame")
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("no active call frame")
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frame_mut();
+        let byte = frame.closure.function.chunk.code[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_short(&mut self) -> usize {
+        let high = self.read_byte();
+        let low = self.read_byte();
+        u16::from_be_bytes([high, low]) as usize
+    }
+
+    fn read_constant(&mut self) -> Value {
+        let index = self.read_short();
+        self.frame().closure.function.chunk.constants[index].clone()
+    }
+
+    fn read_string(&mut self) -> Rc<str> {
+        match self.read_constant() {
+            Value::Str(name) => name,
+            _ => Rc::from(""),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("value stack underflow")
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn pop_numbers(&mut self) -> Result<(f64, f64), LoxError> {
+        match (self.peek(1), self.peek(0)) {
+            (Value::Number(a), Value::Number(b)) => {
+                let (a, b) = (*a, *b);
+                self.pop();
+                self.pop();
+                Ok((a, b))
+            }
+            _ => Err(self.runtime_error("Operands must be numbers.")),
+        }
+    }
+
+    fn runtime_error(&self, message: &str) -> LoxError {
+        LoxError::runtime_error_on_line(self.current_line(), message)
+    }
+
+    // The source line of the instruction being executed.
+    fn current_line(&self) -> usize {
+        self.frames.last().map_or(0, |frame| {
+            frame.closure.function.chunk.line(frame.ip.saturating_sub(1))
+        })
+    }
+
+    fn reset_stack(&mut self) {
+        self.stack.clear();
+        self.frames.clear();
+        self.open_upvalues.clear();
+    }
+}
+
+fn clock(_arguments: &[Value]) -> Result<Value, LoxError> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| LoxError::runtime_error_on_line(0, &err.to_string()))?;
+    Ok(Value::Number(elapsed.as_secs_f64()))
+}
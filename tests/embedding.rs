@@ -0,0 +1,88 @@
+// Drives both backends through the library API, the way an embedder would,
+// to check that natives registered from Rust can be called from Lox code.
+use crafting_interpreters::error::LoxError;
+use crafting_interpreters::interpreter::Interpreter;
+use crafting_interpreters::parser::Parser;
+use crafting_interpreters::resolver::Resolver;
+use crafting_interpreters::scanner::Scanner;
+use crafting_interpreters::token::Token;
+use crafting_interpreters::value::Value;
+use crafting_interpreters::vm::value::Value as VmValue;
+use crafting_interpreters::vm::VM;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn tokens(source: &str) -> Vec<Token> {
+    Scanner::new(source.to_string())
+        .scan_tokens()
+        .expect("source should scan")
+        .clone()
+}
+
+fn run_tree(interpreter: &Interpreter, source: &str) -> Result<(), LoxError> {
+    let statements = Parser::new(tokens(source))
+        .parse()
+        .expect("source should parse");
+    let diagnostics = Resolver::new().resolve_program(&statements);
+    assert!(
+        !diagnostics.iter().any(LoxError::is_error),
+        "{diagnostics:?}"
+    );
+    interpreter.interpret(&statements)
+}
+
+const SCRIPT: &str =
+    "var total = add(1, 2);\nrecord(\"total\", total);\nrecord(greet(), greet() == \"hi\");";
+
+#[test]
+fn natives_registered_on_the_tree_walker_can_be_called() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let interpreter = Interpreter::new();
+    interpreter.register_native("add", 2, |args| match args {
+        [Value::Number(a), Value::Number(b)] => Ok(Value::Number(a + b)),
+        _ => Err(LoxError::runtime_error_on_line(
+            0,
+            "Operands must be numbers.",
+        )),
+    });
+    interpreter.register_native("greet", 0, |_| Ok(Value::from("hi".to_string())));
+    let record = Rc::clone(&seen);
+    interpreter.register_native("record", 2, move |args| {
+        record.borrow_mut().push(format!("{} {}", args[0], args[1]));
+        Ok(Value::Nil)
+    });
+
+    run_tree(&interpreter, SCRIPT).expect("script should run");
+    assert_eq!(*seen.borrow(), ["total 3", "hi true"]);
+
+    let err = run_tree(&interpreter, "print 1;\nadd(1, nil);").unwrap_err();
+    assert_eq!(err.message(), "Operands must be numbers.");
+    assert_eq!(err.details().line, 2);
+}
+
+#[test]
+fn natives_registered_on_the_vm_can_be_called() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut vm = VM::new();
+    vm.register_native("add", 2, |args| match args {
+        [VmValue::Number(a), VmValue::Number(b)] => Ok(VmValue::Number(a + b)),
+        _ => Err(LoxError::runtime_error_on_line(
+            0,
+            "Operands must be numbers.",
+        )),
+    });
+    vm.register_native("greet", 0, |_| Ok(VmValue::Str(Rc::from("hi"))));
+    let record = Rc::clone(&seen);
+    vm.register_native("record", 2, move |args| {
+        record.borrow_mut().push(format!("{} {}", args[0], args[1]));
+        Ok(VmValue::Nil)
+    });
+
+    vm.interpret(&tokens(SCRIPT)).expect("script should run");
+    assert_eq!(*seen.borrow(), ["total 3", "hi true"]);
+
+    let errors = vm.interpret(&tokens("print 1;\nadd(1, nil);")).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message(), "Operands must be numbers.");
+    assert_eq!(errors[0].details().line, 2);
+}
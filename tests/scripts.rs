@@ -0,0 +1,201 @@
+// Runs Lox scripts through the interpreter binary, the way a user would, and
+// checks what they print and how the process exits. Most tests run every
+// script on both backends, which should behave identically.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const BACKENDS: [&str; 2] = ["tree", "vm"];
+
+struct Run {
+    stdout: String,
+    stderr: String,
+    code: Option<i32>,
+}
+
+fn run(backend: &str, name: &str, source: &str) -> Run {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}-{backend}.lox"));
+    fs::write(&path, source).expect("script should be writable");
+    let output = Command::new(env!("CARGO_BIN_EXE_crafting-interpreters"))
+        .arg(format!("--backend={backend}"))
+        .arg(&path)
+        .output()
+        .expect("interpreter should start");
+    Run {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        code: output.status.code(),
+    }
+}
+
+// The output a script in tests/scripts expects, from its `// expect: `
+// comments.
+fn expected_output(source: &str) -> String {
+    source
+        .lines()
+        .filter_map(|line| line.split_once("// expect: "))
+        .map(|(_, expected)| format!("{expected}\n"))
+        .collect()
+}
+
+#[test]
+fn scripts_print_what_they_expect_on_both_backends() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scripts");
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("tests/scripts should exist")
+        .map(|entry| entry.expect("entry should be readable").path())
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let source = fs::read_to_string(&path).expect("script should be readable");
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let expected = expected_output(&source);
+        for backend in BACKENDS {
+            let run = run(backend, &name, &source);
+            assert_eq!(run.code, Some(0), "{name} on {backend}: {}", run.stderr);
+            assert_eq!(run.stdout, expected, "{name} on {backend}");
+        }
+    }
+}
+
+#[test]
+fn both_backends_report_the_same_errors() {
+    let cases = [
+        ("print this;", 65, "Can't use 'this' outside of a class."),
+        ("class A < A {}", 65, "A class can't inherit from itself."),
+        (
+            "class A { m() { return super.m(); } }",
+            65,
+            "Can't use 'super' in a class with no superclass.",
+        ),
+        (
+            "class A { init() { return 1; } }",
+            65,
+            "Can't return a value from an initializer.",
+        ),
+        (
+            "var B = 1; class A < B {}",
+            70,
+            "Superclass must be a class.",
+        ),
+        ("class A {} A(1);", 70, "Expected 0 arguments but got 1."),
+        (
+            "var x = 1; print x.y;",
+            70,
+            "Only instances have properties.",
+        ),
+        ("var x = 1; x.y = 2;", 70, "Only instances have fields."),
+        (
+            "class A {} print A().nope;",
+            70,
+            "Undefined property 'nope'.",
+        ),
+        (
+            "print 1 + \"a\";",
+            70,
+            "Operands must be two numbers or two strings.",
+        ),
+        ("print -\"a\";", 70, "Operand must be a number."),
+        ("print nope;", 70, "Undefined variable 'nope'."),
+    ];
+    for (index, (source, code, message)) in cases.into_iter().enumerate() {
+        for backend in BACKENDS {
+            let run = run(backend, &format!("error{index}"), source);
+            let first_line = run.stderr.lines().next().unwrap_or_default();
+            assert_eq!(
+                (run.code, first_line),
+                (Some(code), format!("error: {message}").as_str()),
+                "{source} on {backend}"
+            );
+        }
+    }
+}
+
+#[test]
+fn both_backends_report_every_syntax_error() {
+    let source = "fun f() {\n  var = 1;\n  print 2;\n}\n\
+                  class A {\n  m() { print ; }\n}\n\
+                  { var x = ; print x; }\n\
+                  print 4";
+    for backend in BACKENDS {
+        let run = run(backend, "syntax_errors", source);
+        // Each message, then its `line:column` with the file name cut off.
+        let located: Vec<String> = run
+            .stderr
+            .lines()
+            .filter_map(|line| match line.split_once("--> ") {
+                Some((_, location)) => {
+                    let (rest, column) = location.rsplit_once(':')?;
+                    let (_, line) = rest.rsplit_once(':')?;
+                    Some(format!("{line}:{column}"))
+                }
+                None => line.strip_prefix("error: ").map(str::to_string),
+            })
+            .collect();
+        assert_eq!(run.code, Some(65), "{backend}");
+        assert_eq!(
+            located,
+            [
+                "Expect variable name.",
+                "2:7",
+                "Expect expression.",
+                "6:15",
+                "Expect expression.",
+                "8:11",
+                "Expect ';' after value.",
+                "9:8",
+            ],
+            "{backend}: {}",
+            run.stderr
+        );
+    }
+}
+
+#[test]
+fn many_distinct_constants() {
+    let mut source = String::from("var x = 0;\n");
+    for n in 0..400 {
+        source.push_str(&format!("x = x + {n}.5;\n"));
+    }
+    source.push_str("print x;\n");
+    for backend in BACKENDS {
+        let run = run(backend, "many_constants", &source);
+        assert_eq!(run.stdout, "80000\n", "{backend}: {}", run.stderr);
+    }
+}
+
+#[test]
+fn deep_recursion_runs() {
+    let source = "fun f(n) { if (n == 0) return 0; return f(n - 1) + 1; } print f(1000);";
+    for backend in BACKENDS {
+        let run = run(backend, "deep_recursion", source);
+        assert_eq!(
+            (run.stdout.as_str(), run.code),
+            ("1000\n", Some(0)),
+            "{backend}: {}",
+            run.stderr
+        );
+    }
+}
+
+#[test]
+fn unbounded_recursion_is_a_runtime_error() {
+    let source = "fun f(n) { return f(n + 1); } f(0);";
+    for backend in BACKENDS {
+        let run = run(backend, "unbounded_recursion", source);
+        assert_eq!(run.code, Some(70), "{backend}: {}", run.stderr);
+        assert!(run.stderr.contains("Stack overflow."), "{}", run.stderr);
+    }
+}
+
+#[test]
+fn unknown_backend_is_a_usage_error_on_stderr() {
+    let run = run("bytecode", "unknown_backend", "print 1;");
+    assert_eq!((run.stdout.as_str(), run.code), ("", Some(64)));
+    assert_eq!(
+        run.stderr,
+        "Unknown backend 'bytecode': expected 'tree' or 'vm'.\n"
+    );
+}